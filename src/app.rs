@@ -1,14 +1,23 @@
 //! The main module.
 //! implements App and all of its features
 
-use std::{error::Error, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    path::Path,
+    time::{Duration, Instant},
+};
 
-use crossterm::event::KeyCode;
+use chrono::Local;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 use crate::{
-    parse::todo::{self, Items},
-    popup::{self, Popup},
-    static_info::StaticInfo,
+    filter::{self, Context, Filter},
+    parse::todo::{self, format_tags, Items},
+    popup::{self, ListAction, Popup},
+    static_info::{StaticInfo, DEFAULT_BACKUPS},
+    undo::History,
+    Score,
 };
 
 /// The current screen that should be shown to
@@ -28,8 +37,29 @@ pub struct App {
     pub static_information: StaticInfo,
     /// What layout the screen is currently in. These layouts contain information about the state
     pub layout: ScreenLayout,
+    /// Are there changes that haven't been saved to disk yet?
+    pub dirty: bool,
+    /// When the last unsaved change was made
+    pub last_change: Option<Instant>,
+    /// How long to wait after the last change before saving automatically.
+    /// `None` disables autosaving
+    pub autosave: Option<Duration>,
+    /// The item that was picked using `b` to block the next item `b` is pressed on
+    pub blocker: Option<todo::Id>,
+    /// Ask before deleting items that have subtasks or a long description
+    pub confirm_deletes: bool,
 }
 
+/// How long the app waits after the last change before autosaving by default
+pub const DEFAULT_AUTOSAVE: Duration = Duration::from_secs(30);
+
+/// Deleting an item with a description longer than this many chars has to be
+/// confirmed, see [`App::confirm_deletes`]
+pub const LONG_DESCRIPTION: usize = 200;
+
+/// The name of the view that shows the items of every list together
+pub const ALL_LISTS: &str = "All lists";
+
 impl App {
     /// Takes files and makes an app from them
     ///
@@ -39,31 +69,235 @@ impl App {
     where
         P: AsRef<Path>,
     {
+        let static_information = StaticInfo::from_or_backup(lists, help, DEFAULT_BACKUPS)?;
+        let status = static_information.restored_from.as_ref().map(|x| {
+            format!(
+                "The lists file couldn't be read, opened {} instead",
+                x.display()
+            )
+        });
         Ok(Self {
-            layout: ScreenLayout::ListChoice,
-            static_information: StaticInfo::from(lists, help)?,
+            layout: ScreenLayout::ListChoice(ListChoiceState {
+                selected: (!static_information.list_names().is_empty()).then_some(0),
+                status,
+                ..Default::default()
+            }),
+            static_information,
+            dirty: false,
+            last_change: None,
+            autosave: Some(DEFAULT_AUTOSAVE),
+            blocker: None,
+            confirm_deletes: true,
         })
     }
+
+    /// Records that something was changed and will need saving
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.last_change = Some(Instant::now());
+    }
+
+    /// Saves every list, including the one currently opened
+    ///
+    /// # Errors
+    /// See [`StaticInfo::save`]
+    pub fn save(&mut self) -> Result<(), Box<dyn Error>> {
+        let current = match self.layout {
+            ScreenLayout::Small(ref state) => match state.sources {
+                Some(ref sources) => {
+                    self.static_information
+                        .write_back(&state.current_data, sources);
+                    None
+                }
+                None => Some((state.current_list.as_str(), &state.current_data)),
+            },
+            ScreenLayout::ListChoice(_) => None,
+        };
+        self.static_information.save(current)?;
+        self.dirty = false;
+        self.last_change = None;
+        Ok(())
+    }
+
+    /// Saves and says how that went in the status row
+    fn save_with_status(&mut self) {
+        let status = match self.save() {
+            Ok(()) => "Saved".to_string(),
+            Err(e) => format!("Failed to save: {e}"),
+        };
+        self.set_status(status);
+    }
+
+    /// Shows a message in the status row if there is one
+    pub fn set_status(&mut self, status: String) {
+        match self.layout {
+            ScreenLayout::Small(ref mut state) => state.status = Some(status),
+            ScreenLayout::ListChoice(ref mut state) => state.status = Some(status),
+        }
+    }
+
+    /// Moves the list called `name` out of [`StaticInfo`] and shows it.
+    /// Smart lists get copies of the items they collect instead
+    pub fn open_list(&mut self, name: &str) {
+        if let Some(search) = self.static_information.searches.get(name) {
+            let search = search.clone();
+            self.open_smart_list(name, &search);
+            return;
+        }
+        let Some(current_data) = self.static_information.get(name) else {
+            return;
+        };
+        self.show_list(name, current_data, None);
+    }
+
+    /// Shows copies of the items of every list that match `search` under the
+    /// name `name`. See [`StaticInfo::smart_list`]
+    pub fn open_smart_list(&mut self, name: &str, search: &str) {
+        match self.static_information.smart_list(search) {
+            Ok((items, sources)) => self.show_list(name, items, Some(sources)),
+            Err(e) => self.set_status(format!("Can't open {name}: {e}")),
+        }
+    }
+
+    fn show_list(
+        &mut self,
+        name: &str,
+        current_data: Items<todo::Item>,
+        sources: Option<HashMap<todo::Id, String>>,
+    ) {
+        self.layout = ScreenLayout::Small(State {
+            current_selection: CurrentSelection::Menu,
+            popup: None,
+            title: name.to_string(),
+            selected: None,
+            substate: None,
+            status: None,
+            tag_filter: None,
+            group_by_tag: false,
+            current_list: name.to_string(),
+            current_data,
+            sources,
+            history: History::default(),
+        });
+    }
+
+    /// Puts the currently opened list back into [`StaticInfo`] and goes back
+    /// to the list picker. The items of a smart list go back into their lists
+    pub fn close_list(&mut self) {
+        let layout = std::mem::replace(
+            &mut self.layout,
+            ScreenLayout::ListChoice(ListChoiceState::default()),
+        );
+        let ScreenLayout::Small(state) = layout else {
+            self.layout = layout;
+            return;
+        };
+        match state.sources {
+            Some(ref sources) => self
+                .static_information
+                .write_back(&state.current_data, sources),
+            None => self
+                .static_information
+                .insert(state.current_list.clone(), state.current_data),
+        }
+        let selected = self
+            .static_information
+            .list_names()
+            .iter()
+            .position(|x| **x == state.current_list);
+        self.layout = ScreenLayout::ListChoice(ListChoiceState {
+            selected,
+            popup: None,
+            status: None,
+        });
+    }
+
+    /// Should be called regularly. Saves the lists once they have been left
+    /// untouched for [`Self::autosave`]
+    pub fn tick(&mut self) {
+        let (Some(autosave), Some(last_change)) = (self.autosave, self.last_change) else {
+            return;
+        };
+        if !self.dirty || last_change.elapsed() < autosave {
+            return;
+        }
+        let status = match self.save() {
+            Ok(()) => "Autosaved".to_string(),
+            Err(e) => {
+                // Don't retry on every tick
+                self.last_change = Some(Instant::now());
+                format!("Failed to autosave: {e}")
+            }
+        };
+        self.set_status(status);
+    }
 }
 
 impl App {
     /// Changes what item is selected.
     pub fn change_menu_item(state: &mut State, dir: &Direction) {
-        let len = state.current_data.amount();
+        let len = state.visible_items().len();
         if len == 0 {
             return;
         }
-        match dir {
-            Direction::Up => {
-                state.selected = state.selected.map_or(Some(0), |x| Some((x + 1) % len));
-            }
-            Direction::Down => {
-                state.selected = state.selected.map_or_else(
-                    || Some(state.current_data.amount() - 1),
-                    |x| Some((x + len - 1) % len),
-                );
+        let selected = state.selected_position();
+        let selected = match dir {
+            Direction::Up => selected.map_or(0, |x| (x + 1) % len),
+            Direction::Down => selected.map_or(len - 1, |x| (x + len - 1) % len),
+        };
+        state.select_position(Some(selected));
+    }
+
+    /// Swaps the selected item with the shown item next to it that has the same parent.
+    /// Returns whether it was moved
+    pub fn move_item(state: &mut State, dir: &Direction) -> bool {
+        let Some(selected) = state.selected_item() else {
+            return false;
+        };
+        let visible = state.visible_items();
+        let siblings = state
+            .current_data
+            .tree()
+            .remove(&state.current_data[selected].parent)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|x| visible.contains(x))
+            .collect::<Vec<_>>();
+        let Some(position) = siblings.iter().position(|&x| x == selected) else {
+            return false;
+        };
+        let other = match dir {
+            Direction::Up => siblings.get(position + 1),
+            Direction::Down => position.checked_sub(1).map(|x| &siblings[x]),
+        };
+        let Some(&other) = other else {
+            return false;
+        };
+        state.checkpoint(format!("moving \"{}\"", state.current_data[selected].title));
+        state.current_data.swap(selected, other);
+        true
+    }
+
+    /// Collapses or expands the subtasks of the selected item. Trying to collapse
+    /// an item without shown subtasks selects its parent instead.
+    /// Returns whether anything was collapsed or expanded
+    pub fn fold(state: &mut State, collapse: bool) -> bool {
+        let Some(selected) = state.selected_item() else {
+            return false;
+        };
+        let has_subtasks = !state.current_data.descendants(selected).is_empty();
+        let item = &mut state.current_data[selected];
+        if collapse && (item.collapsed || !has_subtasks) {
+            if item.parent.is_some() {
+                state.selected = item.parent;
             }
+            return false;
         }
+        if !has_subtasks || item.collapsed == collapse {
+            return false;
+        }
+        item.collapsed = collapse;
+        true
     }
 
     /// Sets the popup field sensibly
@@ -72,29 +306,73 @@ impl App {
     /// This function panics when opening up a popup when already in a popup
     pub fn edit(state: &mut State) {
         assert!(state.popup.is_none(), "we can't already be in a popup");
-        if state.selected.is_none() {
+        let Some(loc) = state.selected_item() else {
             return;
-        }
-        let loc = state.selected.unwrap();
+        };
         let option = &state.current_data[loc];
         state.popup = Some(Popup::Edit {
             title: option.title.to_string(),
             description: option.description.to_string(),
+            tags: format_tags(&option.tags),
+            due: option.due.map(|x| x.to_string()).unwrap_or_default(),
+            scheduled: option.scheduled.map(|x| x.to_string()).unwrap_or_default(),
+            repeat: option
+                .repeat
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            error: None,
             editing: CurrentEdit::Title,
-            to_change: Some(loc),
+            to_change: Some(option.id),
+            parent: None,
+        });
+    }
+    /// Opens the popup to pick the list the selected item is moved or copied
+    /// to, starting at the list it is in now
+    pub fn pick_list(state: &mut State, info: &StaticInfo, copy: bool) {
+        let names = info.item_list_names();
+        // The opened list isn't one of them unless it is a smart list
+        if names.is_empty() {
+            state.status = Some("There is no other list".to_string());
+            return;
+        }
+        let list = state.selected.map(|x| state.list_of(x).to_string());
+        let position = names.iter().position(|x| Some(*x) == list.as_ref());
+        state.popup = Some(Popup::PickList {
+            selected: position.unwrap_or_default(),
+            copy,
+        });
+    }
+
+    /// Opens the popup to name the search before saving it as a smart list
+    pub fn save_search(state: &mut State) {
+        let Some(search) = state.search().map(ToString::to_string) else {
+            return;
+        };
+        state.popup = Some(Popup::ListName {
+            name: String::new(),
+            action: ListAction::SaveSearch(search),
         });
     }
-    /// Sets the state to Add a new item sensibly
+
+    /// Sets the state to Add a new item sensibly. It becomes a subtask of `parent`
     ///
     /// # Panics
     /// Panics when opening a popup whilst already being in a popup
-    pub fn add(state: &mut State) {
+    pub fn add(state: &mut State, parent: Option<todo::Id>) {
         assert!(state.popup.is_none(), "we can't already be in a popup");
         state.popup = Some(Popup::Edit {
             title: String::new(),
             description: String::new(),
+            // New items inherit the tag that's being filtered by so they don't vanish
+            tags: state.tag_filter.clone().unwrap_or_default(),
+            due: String::new(),
+            scheduled: String::new(),
+            repeat: String::new(),
+            error: None,
             editing: CurrentEdit::Title,
             to_change: None,
+            parent,
         });
     }
 }
@@ -114,6 +392,10 @@ pub enum Direction {
 pub enum CurrentEdit {
     Title,
     Body,
+    Tags,
+    Due,
+    Scheduled,
+    Repeat,
 }
 
 /// Contains substates that should be accessible on every screen
@@ -133,102 +415,446 @@ pub enum SubstateMode {
 }
 
 impl App {
+    /// Handles a key press. Unlike [`Self::handle_input`] this knows about
+    /// modifiers, Ctrl-r redoes the latest undone change
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<bool> {
+        let in_menu = matches!(
+            self.layout,
+            ScreenLayout::Small(State {
+                popup: None,
+                current_selection: CurrentSelection::Menu,
+                ref substate,
+                ..
+            }) if substate.as_ref().is_none_or(|x| !x.in_state)
+        );
+        if in_menu
+            && key.code == KeyCode::Char('r')
+            && key.modifiers.contains(KeyModifiers::CONTROL)
+        {
+            self.undo(true);
+            return None;
+        }
+        self.handle_input(key.code)
+    }
+
     /// Handles an input
     #[allow(clippy::missing_panics_doc)]
     pub fn handle_input(&mut self, key: KeyCode) -> Option<bool> {
         match self.layout {
             ScreenLayout::Small(ref mut state) => {
+                state.status = None;
                 match Self::handle_substate(state, key) {
                     SubstateReturn::Continue => (),
-                    SubstateReturn::Exit => return None,
+                    SubstateReturn::Exit => {
+                        // The best match is selected while searching the list
+                        if state.popup.is_none() && state.substate.is_some() {
+                            state.select_position(Some(0));
+                        }
+                        return None;
+                    }
                     SubstateReturn::Select => {
                         if let Some(Popup::Help(ref mut x)) = state.popup {
                             *x = 0;
+                        } else {
+                            state.select_position(Some(0));
                         }
                         return None;
                     }
                 }
-                if let Some(ref mut popup) = state.popup {
-                    match popup.handle_input(key, &self.static_information.help) {
-                        popup::ReturnAction::Exit => state.popup = None,
-                        popup::ReturnAction::Nothing => {}
-                        popup::ReturnAction::Edit(x, new_val) => {
-                            self.static_information
-                                .lists
-                                .get_mut(&state.current_list)
-                                .unwrap()[x] = new_val.into();
-                            state.popup = None;
-                        }
-                        popup::ReturnAction::Add(new_val) => {
-                            self.static_information
-                                .lists
-                                .get_mut(&state.current_list)
-                                .unwrap()
-                                .add(new_val.into());
-                            state.popup = None;
-                        }
-                        popup::ReturnAction::EnterSubState(x) => {
-                            state.substate = Some(Substate {
-                                in_state: true,
-                                substate_mode: x,
-                            });
-                        }
-                    };
-                } else if let Some(x) = Self::handle_main_menu(state, key) {
-                    return Some(x);
-                }
-                None
-            }
-            ScreenLayout::ListChoice => {
-                self.layout = ScreenLayout::Small(State {
-                    current_selection: CurrentSelection::Menu,
-                    popup: None,
-                    title: "Andy!".to_string(),
-                    selected: None,
-                    substate: None,
-                    current_list: "AndyCo".to_string(),
-                    current_data: self.static_information.get("AndyCo").unwrap(),
+                if state.popup.is_some() {
+                    self.handle_popup(key)
+                } else {
+                    self.handle_main_menu(key)
+                }
+            }
+            ScreenLayout::ListChoice(ref mut state) => {
+                state.status = None;
+                if state.popup.is_some() {
+                    self.handle_list_choice_popup(key)
+                } else {
+                    self.handle_list_choice(key)
+                }
+            }
+        }
+    }
+
+    /// Quits the app, but only if saving went fine when asked to save
+    fn quit(&mut self, save: bool) -> Option<bool> {
+        if save {
+            if let Err(e) = self.save() {
+                self.set_status(format!("Failed to save: {e}"));
+                return None;
+            }
+        }
+        Some(true)
+    }
+
+    /// Handles inputs when a popup is shown above a list
+    fn handle_popup(&mut self, key: KeyCode) -> Option<bool> {
+        let ScreenLayout::Small(ref mut state) = self.layout else {
+            return None;
+        };
+        let popup = state.popup.as_mut()?;
+        match popup.handle_input(key, &self.static_information.help) {
+            popup::ReturnAction::Exit => {
+                // The help popup's search shouldn't stay around to filter the list
+                if matches!(state.popup, Some(Popup::Help(_))) {
+                    state.substate = None;
+                }
+                state.popup = None;
+            }
+            popup::ReturnAction::Nothing => {}
+            popup::ReturnAction::Edit(id, new_val) => {
+                if let Some(x) = state.current_data.position(id) {
+                    state.checkpoint(format!("editing \"{}\"", state.current_data[x].title));
+                    state.current_data.modify(x, |item| item.update(new_val));
+                }
+                state.popup = None;
+                self.mark_dirty();
+            }
+            popup::ReturnAction::Add(mut new_val) => {
+                // A smart list isn't a real list, the item has to go somewhere else
+                if !state.adopt(new_val.id, new_val.parent.or(state.selected)) {
+                    self.set_status(
+                        "Select an item in the list the new one belongs in".to_string(),
+                    );
+                    return None;
+                }
+                state.checkpoint(format!("adding \"{}\"", new_val.title));
+                new_val.created = Some(Local::now());
+                // Make sure the new subtask can be seen
+                if let Some(parent) = new_val.parent.and_then(|x| state.current_data.position(x)) {
+                    state.current_data[parent].collapsed = false;
+                }
+                state.selected = Some(new_val.id);
+                state.current_data.add(new_val);
+                state.popup = None;
+                self.mark_dirty();
+            }
+            popup::ReturnAction::Quit { save } => {
+                state.popup = None;
+                return self.quit(save);
+            }
+            popup::ReturnAction::EnterSubState(x) => {
+                state.substate = Some(Substate {
+                    in_state: true,
+                    substate_mode: x,
+                });
+            }
+            popup::ReturnAction::Transfer { list, copy } => {
+                state.popup = None;
+                self.transfer(list, copy);
+            }
+            popup::ReturnAction::Delete => {
+                state.popup = None;
+                self.delete(false);
+            }
+            popup::ReturnAction::RestoreDeleted(x) => self.empty_trash(x, false),
+            popup::ReturnAction::Purge(x) => self.empty_trash(x, true),
+            popup::ReturnAction::Restore(x) => {
+                if let Some(item) = state.current_data.archive.get(x) {
+                    state.checkpoint(format!("restoring \"{}\"", item.title));
+                }
+                state.current_data.restore(x);
+                if state.current_data.archive.is_empty() {
+                    state.popup = None;
+                }
+                self.mark_dirty();
+            }
+            popup::ReturnAction::List(action @ ListAction::SaveSearch(_), name) => {
+                state.popup = None;
+                // The opened list isn't in the static information
+                if name.trim() == state.current_list {
+                    self.set_status(format!("A list called {} already exists", name.trim()));
+                } else {
+                    self.manage_list(&action, &name);
+                }
+            }
+            // Lists are only managed from the list picker
+            popup::ReturnAction::List(..) | popup::ReturnAction::DeleteList(_) => {
+                state.popup = None;
+            }
+        }
+        // Keep the selection within whatever the popup shows
+        if let ScreenLayout::Small(ref mut state) = self.layout {
+            // The history starts with a line for when the item was created
+            let history = if matches!(state.popup, Some(Popup::History(_))) {
+                state
+                    .selected_item()
+                    .map_or(0, |x| state.current_data[x].history.len() + 1)
+            } else {
+                0
+            };
+            let (x, len) = match state.popup {
+                Some(Popup::Archive(ref mut x)) => (x, state.current_data.archive.len()),
+                Some(Popup::Trash(ref mut x)) => (x, state.current_data.trash.len()),
+                Some(Popup::PickList {
+                    ref mut selected, ..
+                }) => (selected, self.static_information.lists.len()),
+                Some(Popup::History(ref mut x)) => (x, history),
+                _ => return None,
+            };
+            *x = (*x).min(len.saturating_sub(1));
+        }
+        None
+    }
+
+    /// Handles inputs when a popup is shown above the list picker
+    fn handle_list_choice_popup(&mut self, key: KeyCode) -> Option<bool> {
+        let ScreenLayout::ListChoice(ref mut state) = self.layout else {
+            return None;
+        };
+        let popup = state.popup.as_mut()?;
+        match popup.handle_input(key, &self.static_information.help) {
+            popup::ReturnAction::Quit { save } => {
+                state.popup = None;
+                return self.quit(save);
+            }
+            popup::ReturnAction::List(action, name) => {
+                state.popup = None;
+                self.manage_list(&action, &name);
+            }
+            popup::ReturnAction::DeleteList(name) => {
+                state.popup = None;
+                if self.static_information.remove_list(&name) {
+                    self.mark_dirty();
+                    self.set_status(format!("Deleted {name}"));
+                }
+                self.select_list(None);
+            }
+            popup::ReturnAction::Nothing => {}
+            _ => state.popup = None,
+        }
+        None
+    }
+
+    /// Creates, renames or duplicates a list and selects the result
+    fn manage_list(&mut self, action: &ListAction, name: &str) {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            self.set_status("A list needs a name".to_string());
+            return;
+        }
+        let done = match *action {
+            ListAction::Create => self.static_information.create_list(name.clone()),
+            ListAction::Rename(ref from) => self.static_information.rename_list(from, name.clone()),
+            ListAction::Duplicate(ref from) => {
+                self.static_information.duplicate_list(from, name.clone())
+            }
+            ListAction::SaveSearch(ref search) => self
+                .static_information
+                .save_search(name.clone(), search.clone()),
+        };
+        if !done {
+            self.set_status(format!("A list called {name} already exists"));
+            return;
+        }
+        self.mark_dirty();
+        if let ListAction::SaveSearch(_) = action {
+            self.set_status(format!("Saved the search as {name}"));
+        }
+        self.select_list(Some(&name));
+    }
+
+    /// Moves the cursor of the list picker to the list called `name`.
+    /// Without a name the cursor is just kept in bounds
+    fn select_list(&mut self, name: Option<&str>) {
+        let names = self.static_information.list_names();
+        let ScreenLayout::ListChoice(ref mut state) = self.layout else {
+            return;
+        };
+        state.selected = name
+            .and_then(|name| names.iter().position(|x| *x == name))
+            .or_else(|| {
+                state
+                    .selected
+                    .map(|x| x.min(names.len().saturating_sub(1)))
+                    .filter(|_| !names.is_empty())
+            });
+    }
+
+    fn handle_list_choice(&mut self, key: KeyCode) -> Option<bool> {
+        let ScreenLayout::ListChoice(ref mut state) = self.layout else {
+            return None;
+        };
+        let len = self.static_information.list_names().len();
+        let selected_name = state
+            .selected
+            .and_then(|x| self.static_information.list_names().get(x).copied())
+            .cloned();
+        match key {
+            // quit, but ask first if something would get lost
+            KeyCode::Char('q') | KeyCode::Esc if self.dirty => {
+                state.popup = Some(Popup::ConfirmQuit);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Some(true),
+            KeyCode::Char('j') | KeyCode::Down if len != 0 => {
+                state.selected = Some(state.selected.map_or(0, |x| (x + 1) % len));
+            }
+            KeyCode::Char('k') | KeyCode::Up if len != 0 => {
+                state.selected = Some(state.selected.map_or(len - 1, |x| (x + len - 1) % len));
+            }
+            KeyCode::Enter => {
+                if let Some(name) = selected_name {
+                    self.open_list(&name);
+                }
+            }
+            // Create a new list
+            KeyCode::Char('a') => {
+                state.popup = Some(Popup::ListName {
+                    name: String::new(),
+                    action: ListAction::Create,
                 });
-                None
             }
+            // Rename the selected list
+            KeyCode::Char('r') => {
+                if let Some(name) = selected_name {
+                    state.popup = Some(Popup::ListName {
+                        name: name.clone(),
+                        action: ListAction::Rename(name),
+                    });
+                }
+            }
+            // Copy the selected list
+            KeyCode::Char('c') => {
+                if let Some(name) = selected_name {
+                    state.popup = Some(Popup::ListName {
+                        name: format!("{name} copy"),
+                        action: ListAction::Duplicate(name),
+                    });
+                }
+            }
+            // Show the items of every list together
+            KeyCode::Char('A') => self.open_smart_list(ALL_LISTS, ""),
+            // Delete the selected list
+            KeyCode::Char('d') => {
+                if let Some(name) = selected_name {
+                    state.popup = Some(Popup::ConfirmDeleteList(name));
+                }
+            }
+            // Save all lists to disk
+            KeyCode::Char('s') => self.save_with_status(),
+            _ => (),
         }
+        None
     }
 
-    fn handle_main_menu(state: &mut State, key: KeyCode) -> Option<bool> {
+    fn handle_main_menu(&mut self, key: KeyCode) -> Option<bool> {
+        let ScreenLayout::Small(ref mut state) = self.layout else {
+            return None;
+        };
         match state.current_selection {
             CurrentSelection::Menu => match key {
-                // quit
+                // Stop searching
+                KeyCode::Esc if state.substate.is_some() => state.substate = None,
+                // quit, but ask first if something would get lost
+                KeyCode::Char('q') | KeyCode::Esc if self.dirty => {
+                    state.popup = Some(Popup::ConfirmQuit);
+                }
                 KeyCode::Char('q') | KeyCode::Esc => return Some(true),
+                // Go back to the list picker
+                KeyCode::Backspace => self.close_list(),
                 // Help
-                KeyCode::Char('?') => state.popup = Some(Popup::Help(0)),
+                KeyCode::Char('?') => {
+                    state.substate = None;
+                    state.popup = Some(Popup::Help(0));
+                }
                 // Vim motion + Down key
                 KeyCode::Char('j') | KeyCode::Down => Self::change_menu_item(state, &Direction::Up),
                 // Vim motion + Down key
                 KeyCode::Char('k') | KeyCode::Up => Self::change_menu_item(state, &Direction::Down),
                 // Enter edit mode
                 // TODO: rename edit to something more descriptive
-                KeyCode::Char('e') if state.selected.is_some() => Self::edit(state),
+                KeyCode::Char('e') if state.selected_item().is_some() => Self::edit(state),
                 // Enter add mode (Add a new item)
-                KeyCode::Char('a') => Self::add(state),
+                KeyCode::Char('a') => Self::add(state, None),
+                // Add a subtask to the entry
+                KeyCode::Char('n') if state.selected_item().is_some() => {
+                    let parent = state.selected;
+                    Self::add(state, parent);
+                }
+                // Collapse or expand the subtasks of the entry
+                KeyCode::Char(x @ ('h' | 'l')) if Self::fold(state, x == 'h') => self.mark_dirty(),
+                // Save all lists to disk
+                KeyCode::Char('s') => self.save_with_status(),
                 // Focus the description
-                KeyCode::Enter => {
-                    if state.selected.is_some() {
-                        state.current_selection = CurrentSelection::Description;
-                    }
+                KeyCode::Enter if state.selected_item().is_some() => {
+                    state.current_selection = CurrentSelection::Description;
                 }
 
                 // Delete entry
-                KeyCode::Char('d') if state.selected.is_some() => {
-                    let selected = unsafe { state.selected.unwrap_unchecked() };
-                    state.current_data.remove(selected);
-                    if selected == state.current_data.amount() {
-                        if state.current_data.is_empty() {
-                            state.selected = None;
-                        } else {
-                            state.selected = Some(selected - 1);
-                        }
+                KeyCode::Char('d') if state.selected_item().is_some() => {
+                    self.delete(self.confirm_deletes);
+                }
+                // Mark the entry as done or not done
+                KeyCode::Char('x') if state.selected_item().is_some() => self.toggle_done(),
+                // Pick the entry as a blocker, or make the picked entry block this one
+                KeyCode::Char('b') if state.selected_item().is_some() => self.block(),
+                // Stop the entry from waiting on anything
+                KeyCode::Char('B') if state.selected_item().is_some() => self.unblock(),
+                // Cycle through the priorities of the entry
+                KeyCode::Char('p') => {
+                    if let Some(selected) = state.selected_item() {
+                        let title = &state.current_data[selected].title;
+                        state.checkpoint(format!("changing the priority of \"{title}\""));
+                        state
+                            .current_data
+                            .modify(selected, todo::Item::cycle_priority);
+                        self.mark_dirty();
+                    }
+                }
+                // Move the entry down or up, keeping the list in that order from now on
+                KeyCode::Char('J') if Self::move_item(state, &Direction::Up) => self.mark_dirty(),
+                KeyCode::Char('K') if Self::move_item(state, &Direction::Down) => self.mark_dirty(),
+                // Sort the entries differently
+                KeyCode::Char('o') => {
+                    state.checkpoint("sorting the list".to_string());
+                    let sort = state.current_data.sort.next();
+                    state.current_data.set_sort(sort);
+                    self.mark_dirty();
+                }
+                // Move every done entry into the archive
+                KeyCode::Char('A') => {
+                    let before = state.snapshot();
+                    let position = state.selected_position();
+                    let amount = state.current_data.archive_done();
+                    if state.selected_item().is_none() {
+                        state.select_position(position);
                     }
+                    if amount != 0 {
+                        state.history.record("archiving".to_string(), before);
+                        self.mark_dirty();
+                    }
+                    self.set_status(format!("Archived {amount} items"));
+                }
+                // Undo the last change
+                KeyCode::Char('u') => self.undo(false),
+                // Show what has been changed about the entry
+                KeyCode::Char('H') if state.selected_item().is_some() => {
+                    state.popup = Some(Popup::History(0));
+                }
+                // Browse the archive
+                KeyCode::Char('v') if !state.current_data.archive.is_empty() => {
+                    state.popup = Some(Popup::Archive(0));
+                }
+                // Browse the deleted entries
+                KeyCode::Char('D') if !state.current_data.trash.is_empty() => {
+                    state.popup = Some(Popup::Trash(0));
+                }
+                // Only show items with the next tag
+                KeyCode::Char('t') => {
+                    state.tag_filter = state.current_data.next_tag(state.tag_filter.as_deref());
                 }
+                // Group items by their tags
+                KeyCode::Char('T') => {
+                    state.group_by_tag = !state.group_by_tag;
+                }
+                // Move or copy the entry into a different list
+                KeyCode::Char(x @ ('m' | 'c')) if state.selected_item().is_some() => {
+                    Self::pick_list(state, &self.static_information, x == 'c');
+                }
+                // Save the search as a smart list
+                KeyCode::Char('S') if state.search().is_some() => Self::save_search(state),
                 KeyCode::Char('/') => {
                     state.substate = Some(Substate {
                         in_state: true,
@@ -238,27 +864,297 @@ impl App {
                 _ => (),
             },
 
-            CurrentSelection::Description => {
-                match key {
-                    // quit
-                    KeyCode::Char('q') => state.current_selection = CurrentSelection::Menu,
-                    // Vim motions
-                    KeyCode::Char('j') | KeyCode::Down => {
-                        if state.selected? != state.current_data.amount() - 1 {
-                            state.current_data[state.selected?].description_scroll += 1;
-                        }
-                    }
-                    KeyCode::Char('k') | KeyCode::Up => {
-                        state.current_data[state.selected?].description_scroll = state.current_data
-                            [state.selected?]
-                            .description_scroll
-                            .saturating_sub(1);
+            CurrentSelection::Description => Self::handle_description(state, key),
+        }
+        None
+    }
+
+    /// Marks the selected item as done or not done and says which items can be
+    /// worked on now because of it
+    fn toggle_done(&mut self) {
+        let ScreenLayout::Small(ref mut state) = self.layout else {
+            return;
+        };
+        let Some(selected) = state.selected_item() else {
+            return;
+        };
+        let item = &state.current_data[selected];
+        let done = if item.is_done() { "not done" } else { "done" };
+        state.checkpoint(format!("marking \"{}\" {done}", item.title));
+        let selected = state.current_data.modify(selected, todo::Item::toggle_done);
+        let item = &state.current_data[selected];
+        let mut status = Vec::new();
+        if item.is_done() {
+            let items = state.lookup(&self.static_information);
+            let unblocked = item
+                .dependents(&items)
+                .into_iter()
+                .filter(|(_, x)| !x.is_blocked(&items))
+                .map(|(_, x)| format!("\"{}\"", x.title))
+                .collect::<Vec<_>>();
+            if !unblocked.is_empty() {
+                status.push(format!("Unblocked {}", unblocked.join(", ")));
+            }
+            if let Some(next) = state.current_data[selected].next_occurrence(Local::now()) {
+                let id = state.current_data[selected].id;
+                state.adopt(next.id, Some(id));
+                if let Some(due) = next.due {
+                    status.push(format!("Next one is due {due}"));
+                }
+                state.current_data.add(next);
+            }
+        } else if let Some(next) = item.next {
+            // The occurrence created when it was done isn't needed anymore
+            if let Some(x) = state
+                .current_data
+                .position(next)
+                .filter(|&x| !state.current_data[x].is_done())
+            {
+                let id = state.current_data[selected].id;
+                let next = state.current_data.remove(x);
+                if let Some(x) = state.current_data.position(id) {
+                    state.current_data.modify(x, |item| item.take_back(next));
+                }
+                status.push("Removed the next occurrence".to_string());
+            }
+        }
+        self.mark_dirty();
+        if !status.is_empty() {
+            self.set_status(status.join(". "));
+        }
+    }
+
+    /// Moves or copies the selected item and its subtasks into the list at
+    /// `index` in [`StaticInfo::item_list_names`], keeping everything about them
+    fn transfer(&mut self, index: usize, copy: bool) {
+        let ScreenLayout::Small(ref mut state) = self.layout else {
+            return;
+        };
+        let names = self.static_information.item_list_names();
+        let (Some(selected), Some(list)) = (state.selected_item(), names.get(index)) else {
+            return;
+        };
+        let list = (*list).clone();
+        let title = state.current_data[selected].title.clone();
+        let action = if copy { "copying" } else { "moving" };
+        let mut before = state.snapshot();
+        // Smart lists only change the other list once they are written back
+        if state.sources.is_none() {
+            before.other = self
+                .static_information
+                .lists
+                .get(&list)
+                .map(|x| (list.clone(), x.clone()));
+        }
+        state
+            .history
+            .record(format!("{action} \"{title}\" to {list}"), before);
+        if copy {
+            let mut copies = state.current_data.copy_tree(selected);
+            copies[0].parent = None;
+            // Smart lists put their items into the real lists later on
+            if let Some(ref mut sources) = state.sources {
+                sources.extend(copies.iter().map(|x| (x.id, list.clone())));
+                for item in copies {
+                    state.current_data.add(item);
+                }
+            } else if let Some(target) = self.static_information.lists.get_mut(&list) {
+                for item in copies {
+                    target.add(item);
+                }
+            }
+        } else if !state.move_to(selected, &list) {
+            let id = state.current_data[selected].id;
+            let position = state.selected_position();
+            let moved = state.current_data.remove_tree(selected);
+            state.select_position(position);
+            if let Some(target) = self.static_information.lists.get_mut(&list) {
+                for mut item in moved {
+                    if item.id == id {
+                        item.parent = None;
                     }
-                    _ => (),
+                    target.add(item);
                 }
             }
         }
-        None
+        self.mark_dirty();
+        let action = if copy { "Copied" } else { "Moved" };
+        self.set_status(format!("{action} \"{title}\" to {list}"));
+    }
+
+    /// Takes the item at `index` and its subtasks out of the trash, either
+    /// back into the list or for good with `purge`
+    fn empty_trash(&mut self, index: usize, purge: bool) {
+        let ScreenLayout::Small(ref mut state) = self.layout else {
+            return;
+        };
+        let Some(item) = state.current_data.trash.get(index) else {
+            return;
+        };
+        if purge {
+            state.checkpoint(format!("purging \"{}\"", item.title));
+            state.current_data.purge(index);
+        } else {
+            state.checkpoint(format!("restoring \"{}\"", item.title));
+            state.current_data.restore_deleted(index);
+        }
+        if state.current_data.trash.is_empty() {
+            state.popup = None;
+        }
+        self.mark_dirty();
+    }
+
+    /// Moves the selected item and its subtasks into the trash. With `confirm`
+    /// items with subtasks or a long description have to be confirmed first
+    fn delete(&mut self, confirm: bool) {
+        let ScreenLayout::Small(ref mut state) = self.layout else {
+            return;
+        };
+        let Some(selected) = state.selected_item() else {
+            return;
+        };
+        let item = &state.current_data[selected];
+        let has_subtasks = !state.current_data.descendants(selected).is_empty();
+        if confirm && (has_subtasks || item.description.chars().count() > LONG_DESCRIPTION) {
+            state.popup = Some(Popup::ConfirmDelete);
+            return;
+        }
+        let title = item.title.clone();
+        state.checkpoint(format!("deleting \"{title}\""));
+        let position = state.selected_position();
+        state.current_data.delete(selected);
+        state.select_position(position);
+        self.mark_dirty();
+        self.set_status(format!("Moved \"{title}\" to the trash"));
+    }
+
+    /// Stops the selected item from waiting on anything
+    fn unblock(&mut self) {
+        let ScreenLayout::Small(ref mut state) = self.layout else {
+            return;
+        };
+        let Some(selected) = state.selected_item() else {
+            return;
+        };
+        let item = &state.current_data[selected];
+        if item.blocked_by.is_empty() {
+            return;
+        }
+        state.checkpoint(format!("unblocking \"{}\"", item.title));
+        state.current_data[selected].blocked_by.clear();
+        self.mark_dirty();
+    }
+
+    /// Undoes the latest change to the opened list, or redoes the latest one
+    /// that was undone
+    pub fn undo(&mut self, redo: bool) {
+        let ScreenLayout::Small(ref mut state) = self.layout else {
+            return;
+        };
+        let lists = &self.static_information.lists;
+        let now = |x: &Snapshot| Snapshot {
+            items: state.current_data.clone(),
+            sources: state.sources.clone(),
+            selected: state.selected,
+            other: x
+                .other
+                .as_ref()
+                .and_then(|(name, _)| Some((name.clone(), lists.get(name)?.clone()))),
+        };
+        let step = if redo {
+            state.history.redo(now)
+        } else {
+            state.history.undo(now)
+        };
+        let Some((change, snapshot)) = step else {
+            let status = if redo {
+                "Nothing to redo"
+            } else {
+                "Nothing to undo"
+            };
+            self.set_status(status.to_string());
+            return;
+        };
+        state.current_data = snapshot.items;
+        // Items that are gone now, like ones that were added, still have to be
+        // taken out of the list they were written back to
+        let mut sources = snapshot.sources;
+        if let (Some(sources), Some(current)) = (&mut sources, &state.sources) {
+            for (&id, list) in current {
+                sources.entry(id).or_insert_with(|| list.clone());
+            }
+        }
+        state.sources = sources;
+        state.selected = snapshot.selected;
+        if let Some((name, list)) = snapshot.other {
+            self.static_information.lists.insert(name, list);
+        }
+        self.mark_dirty();
+        let done = if redo { "Redid" } else { "Undid" };
+        self.set_status(format!("{done} {change}"));
+    }
+
+    /// Picks the selected item as a blocker if none has been picked yet.
+    /// Otherwise the picked item is made to block the selected one
+    fn block(&mut self) {
+        let ScreenLayout::Small(ref mut state) = self.layout else {
+            return;
+        };
+        let Some(selected) = state.selected_item() else {
+            return;
+        };
+        let item = &state.current_data[selected];
+        let (id, title) = (item.id, item.title.clone());
+        let Some(blocker) = self.blocker.take() else {
+            self.blocker = Some(id);
+            self.set_status(format!("Press b on the item \"{title}\" blocks"));
+            return;
+        };
+        if blocker == id {
+            self.set_status("Unpicked".to_string());
+            return;
+        }
+        let items = state.lookup(&self.static_information);
+        let Some(&(_, blocker_item)) = items.get(&blocker) else {
+            self.set_status("The picked item doesn't exist anymore".to_string());
+            return;
+        };
+        let blocker_title = blocker_item.title.clone();
+        // Items waiting on each other could never be done
+        if todo::waits_on(&items, blocker, id) {
+            self.set_status(format!("\"{blocker_title}\" already waits on \"{title}\""));
+            return;
+        }
+        state.checkpoint(format!("blocking \"{title}\""));
+        state.current_data[selected].blocked_by.insert(blocker);
+        self.mark_dirty();
+        self.set_status(format!("\"{blocker_title}\" now blocks \"{title}\""));
+    }
+
+    /// Handles inputs when the description is focused
+    fn handle_description(state: &mut State, key: KeyCode) {
+        match key {
+            // quit
+            KeyCode::Char('q') => state.current_selection = CurrentSelection::Menu,
+            // Vim motions
+            KeyCode::Char('j') | KeyCode::Down
+                if state.selected_position() != Some(state.current_data.amount() - 1) =>
+            {
+                let Some(selected) = state.selected_item() else {
+                    return;
+                };
+                state.current_data[selected].description_scroll += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                let Some(selected) = state.selected_item() else {
+                    return;
+                };
+                state.current_data[selected].description_scroll = state.current_data[selected]
+                    .description_scroll
+                    .saturating_sub(1);
+            }
+            _ => (),
+        }
     }
 
     /// Handles inputs when a substate is focused
@@ -300,11 +1196,23 @@ enum SubstateReturn {
 
 /// The current layout of the screen
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum ScreenLayout {
     /// Everything is at its smallest size
     Small(State),
     /// Selecting the list to load
-    ListChoice,
+    ListChoice(ListChoiceState),
+}
+
+/// State information for the list picker
+#[derive(Debug, Default)]
+pub struct ListChoiceState {
+    /// The index of the currently selected list in [`StaticInfo::list_names`]
+    pub selected: Option<usize>,
+    /// The popup that is shown above everything
+    pub popup: Option<Popup>,
+    /// A message shown in the bottom row, cleared on the next keypress
+    pub status: Option<String>,
 }
 
 /// State information for the main screen layout
@@ -316,13 +1224,283 @@ pub struct State {
     pub popup: Option<Popup>,
     /// The title of the application
     pub title: String,
-    /// The currently selected item (An index)
-    pub selected: Option<usize>,
+    /// The currently selected item
+    pub selected: Option<todo::Id>,
     /// a bool determining whether we are in the substate and
     /// the information associated with it
     pub substate: Option<Substate>,
+    /// A message shown in the bottom row, cleared on the next keypress
+    pub status: Option<String>,
+    /// Only items with this tag are shown
+    pub tag_filter: Option<String>,
+    /// Are items ordered by their tags?
+    pub group_by_tag: bool,
     /// What Todo list are we currently editing?
     pub current_list: String,
     /// What items are in the current list?
     pub current_data: Items<todo::Item>,
+    /// The list each item really is in if this is a smart list. Items that
+    /// were removed from it by undoing stay in here until it is written back
+    pub sources: Option<HashMap<todo::Id, String>>,
+    /// The changes made since the list was opened, so that they can be undone
+    pub history: History<Snapshot>,
+}
+
+/// How the opened list was at some point, see [`State::history`]
+#[derive(Debug)]
+pub struct Snapshot {
+    items: Items<todo::Item>,
+    sources: Option<HashMap<todo::Id, String>>,
+    selected: Option<todo::Id>,
+    /// Another list that was changed at the same time
+    other: Option<(String, Items<todo::Item>)>,
+}
+
+impl State {
+    /// The indices into [`Self::current_data`] of every item that is shown, in
+    /// the order they are shown in
+    #[must_use]
+    pub fn visible_items(&self) -> Vec<usize> {
+        self.visible_rows().into_iter().map(|(x, _)| x).collect()
+    }
+
+    /// Like [`Self::visible_items`], together with how deep in the tree each
+    /// item is shown
+    #[must_use]
+    pub fn visible_rows(&self) -> Vec<(usize, usize)> {
+        // Search results aren't shown as a tree
+        if let Some(Ok(filter)) = self.filter() {
+            return self
+                .search_results(&filter)
+                .into_iter()
+                .map(|x| (x, 0))
+                .collect();
+        }
+        let tree = self.current_data.tree();
+        let positions = self
+            .current_data
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (x.id, i))
+            .collect::<HashMap<_, _>>();
+        // Parents of matching subtasks are shown so the subtasks stay reachable
+        let tagged = self.tag_filter.as_ref().map(|tag| {
+            let mut tagged = HashSet::new();
+            for (i, item) in self.current_data.items.iter().enumerate() {
+                if !item.tags.contains(tag) {
+                    continue;
+                }
+                let mut x = Some(i);
+                while let Some(i) = x.filter(|&i| tagged.insert(i)) {
+                    x = self.current_data[i]
+                        .parent
+                        .and_then(|x| positions.get(&x).copied());
+                }
+            }
+            tagged
+        });
+        // Smart lists might not contain the parents of some items
+        let mut roots = (0..self.current_data.amount())
+            .filter(|&x| {
+                self.current_data[x]
+                    .parent
+                    .is_none_or(|x| !positions.contains_key(&x))
+            })
+            .collect::<Vec<_>>();
+        if self.group_by_tag {
+            // Untagged items go last
+            roots.sort_by_key(|&x| {
+                let tags = &self.current_data[x].tags;
+                (tags.is_empty(), tags.first().cloned())
+            });
+        }
+        let mut visible = Vec::new();
+        self.push_visible(&tree, tagged.as_ref(), &roots, 0, &mut visible);
+        visible
+    }
+
+    /// What the list is being searched for, if it is
+    #[must_use]
+    pub fn search(&self) -> Option<&str> {
+        match &self.substate {
+            Some(Substate {
+                substate_mode: SubstateMode::Filter(search),
+                ..
+            }) if self.popup.is_none() && !search.is_empty() => Some(search),
+            _ => None,
+        }
+    }
+
+    /// The name of the list the item with this id really is in
+    #[must_use]
+    pub fn list_of(&self, id: todo::Id) -> &str {
+        self.sources
+            .as_ref()
+            .and_then(|x| x.get(&id))
+            .unwrap_or(&self.current_list)
+    }
+
+    /// Every item of every list by its id, see [`StaticInfo::lookup`]
+    #[must_use]
+    pub fn lookup<'a>(&'a self, info: &'a StaticInfo) -> todo::Lookup<'a> {
+        let mut items = info.lookup(None);
+        // The items of a smart list replace the ones they were copied from
+        items.extend(
+            self.current_data
+                .items
+                .iter()
+                .map(|x| (x.id, (self.list_of(x.id), x))),
+        );
+        items
+    }
+
+    /// Remembers that the new item with the id `id` goes into the same list as
+    /// the item `like` when this is a smart list.
+    /// Returns false if that list isn't known
+    pub fn adopt(&mut self, id: todo::Id, like: Option<todo::Id>) -> bool {
+        let Some(ref mut sources) = self.sources else {
+            return true;
+        };
+        let Some(list) = like.and_then(|x| sources.get(&x)).cloned() else {
+            return false;
+        };
+        sources.insert(id, list);
+        true
+    }
+
+    /// How the list is now
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            items: self.current_data.clone(),
+            sources: self.sources.clone(),
+            selected: self.selected,
+            other: None,
+        }
+    }
+
+    /// Remembers how the list is now so that the change about to be made can be
+    /// undone. `change` describes it, like "deleting \"Groceries\""
+    pub fn checkpoint(&mut self, change: String) {
+        let snapshot = self.snapshot();
+        self.history.record(change, snapshot);
+    }
+
+    /// Puts the item at `index` and its subtasks into the list called `list`
+    /// once this smart list is written back.
+    /// Returns false if this isn't a smart list
+    pub fn move_to(&mut self, index: usize, list: &str) -> bool {
+        if self.sources.is_none() {
+            return false;
+        }
+        // A subtask can't stay under a parent in another list
+        let parent = self.current_data[index].parent;
+        if parent.is_none_or(|x| self.list_of(x) != list) {
+            self.current_data[index].parent = None;
+        }
+        let mut moved = self.current_data.descendants(index);
+        moved.push(index);
+        let ids = moved
+            .into_iter()
+            .map(|x| self.current_data[x].id)
+            .collect::<Vec<_>>();
+        let Some(ref mut sources) = self.sources else {
+            return false;
+        };
+        for id in ids {
+            sources.insert(id, list.to_string());
+        }
+        true
+    }
+
+    /// The search parsed as a [`Filter`], if the list is being searched
+    #[must_use]
+    pub fn filter(&self) -> Option<Result<Filter, String>> {
+        self.search()
+            .map(|x| filter::parse(x, Local::now().naive_local()))
+    }
+
+    /// Every item matching `filter`, subtasks included, with the best match first
+    fn search_results(&self, filter: &Filter) -> Vec<usize> {
+        // Only the current list is known here, so blockers in other lists are ignored
+        let items = self
+            .current_data
+            .items
+            .iter()
+            .map(|x| (x.id, (self.list_of(x.id), x)))
+            .collect();
+        let now = Local::now().naive_local();
+        let words = filter.words();
+        let mut results = (0..self.current_data.amount())
+            .filter(|&x| {
+                self.tag_filter
+                    .as_ref()
+                    .is_none_or(|tag| self.current_data[x].tags.contains(tag))
+            })
+            .filter(|&x| {
+                let item = &self.current_data[x];
+                let context = Context {
+                    list: self.list_of(item.id),
+                    items: &items,
+                    now,
+                };
+                filter.matches(item, &context)
+            })
+            .map(|x| {
+                let item = &self.current_data[x];
+                let score = words
+                    .iter()
+                    .map(|word| item.score(word).unwrap_or_default())
+                    .sum::<i64>();
+                (score, x)
+            })
+            .collect::<Vec<_>>();
+        results.sort_by_key(|&(score, _)| score);
+        results.into_iter().map(|(_, x)| x).collect()
+    }
+
+    /// Adds the items in `items` that are shown and their shown subtasks to `visible`
+    fn push_visible(
+        &self,
+        tree: &HashMap<Option<todo::Id>, Vec<usize>>,
+        tagged: Option<&HashSet<usize>>,
+        items: &[usize],
+        depth: usize,
+        visible: &mut Vec<(usize, usize)>,
+    ) {
+        for &x in items {
+            if tagged.is_some_and(|tagged| !tagged.contains(&x)) {
+                continue;
+            }
+            visible.push((x, depth));
+            let item = &self.current_data[x];
+            if let Some(children) = tree.get(&Some(item.id)).filter(|_| !item.collapsed) {
+                self.push_visible(tree, tagged, children, depth + 1, visible);
+            }
+        }
+    }
+
+    /// Where the selected item is among the shown items. `None` if it isn't shown
+    #[must_use]
+    pub fn selected_position(&self) -> Option<usize> {
+        let id = self.selected?;
+        self.visible_items()
+            .iter()
+            .position(|&x| self.current_data[x].id == id)
+    }
+
+    /// The index into [`Self::current_data`] of the selected item if it's shown
+    #[must_use]
+    pub fn selected_item(&self) -> Option<usize> {
+        self.visible_items().get(self.selected_position()?).copied()
+    }
+
+    /// Selects the shown item at this position, or the last one if there are fewer
+    pub fn select_position(&mut self, position: Option<usize>) {
+        let visible = self.visible_items();
+        self.selected = position
+            .and_then(|x| visible.get(x.min(visible.len().saturating_sub(1))))
+            .map(|&x| self.current_data[x].id);
+    }
 }
@@ -0,0 +1,556 @@
+//! A small query language for filtering todo items.
+//!
+//! A filter is made up of terms like `tag:work`, `-tag:later`, `due:<1w`, `pri:A`,
+//! `is:done`, `list:AndyCo` or `created:>2026-01-01`. Anything else is searched
+//! for in the title, tags and description. Terms next to each other all have to
+//! match, `OR` allows either side to match and parentheses group terms together
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+};
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::parse::{
+    date,
+    todo::{Item, Lookup, Priority},
+};
+
+/// A parsed filter, see the [module documentation](self)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// Every filter has to match. Nothing at all matches everything
+    All(Vec<Self>),
+    /// At least one filter has to match
+    Any(Vec<Self>),
+    /// The filter mustn't match
+    Not(Box<Self>),
+    /// The text is found in the title, the tags or the description
+    Text(String),
+    /// The item has this tag
+    Tag(String),
+    /// The date compares to the given one like this
+    Date(DateField, Comparison, NaiveDate),
+    /// The item doesn't have this date
+    NoDate(DateField),
+    /// The item has this priority. `None` means no priority at all
+    Priority(Option<Priority>),
+    /// The item is in this state
+    Is(Is),
+    /// The item is in the list with this name
+    List(String),
+}
+
+/// The dates of an item that can be filtered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum DateField {
+    Due,
+    Scheduled,
+    Created,
+    Modified,
+}
+
+/// How a date has to compare to the one in the filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+/// The states used by `is:`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Is {
+    /// Has been done
+    Done,
+    /// Still has to be done
+    Todo,
+    /// Waits on an item that still has to be done
+    Blocked,
+    /// Should have been done already
+    Overdue,
+    /// Comes back after it has been done
+    Recurring,
+}
+
+/// What a filter needs to know besides the item itself
+#[derive(Debug)]
+pub struct Context<'a> {
+    /// The name of the list the item is in
+    pub list: &'a str,
+    /// The items that might block the item
+    pub items: &'a Lookup<'a>,
+    /// The current time
+    pub now: NaiveDateTime,
+}
+
+impl Filter {
+    /// Does the item match this filter?
+    #[must_use]
+    pub fn matches(&self, item: &Item, context: &Context) -> bool {
+        match self {
+            Self::All(filters) => filters.iter().all(|x| x.matches(item, context)),
+            Self::Any(filters) => filters.iter().any(|x| x.matches(item, context)),
+            Self::Not(filter) => !filter.matches(item, context),
+            Self::Text(text) => item.best_match(text).is_some(),
+            Self::Tag(tag) => item.tags.iter().any(|x| x.eq_ignore_ascii_case(tag)),
+            Self::Date(field, comparison, date) => field
+                .get(item)
+                .is_some_and(|x| comparison.holds(x.cmp(date))),
+            Self::NoDate(field) => field.get(item).is_none(),
+            Self::Priority(priority) => item.priority == *priority,
+            Self::Is(Is::Done) => item.is_done(),
+            Self::Is(Is::Todo) => !item.is_done(),
+            Self::Is(Is::Blocked) => item.is_blocked(context.items),
+            Self::Is(Is::Overdue) => {
+                !item.is_done() && item.due.is_some_and(|x| x.is_overdue(context.now))
+            }
+            Self::Is(Is::Recurring) => item.repeat.is_some(),
+            Self::List(list) => list.eq_ignore_ascii_case(context.list),
+        }
+    }
+
+    /// The text that is searched for, except for text that mustn't be found.
+    /// Used to rank and highlight the matches
+    #[must_use]
+    pub fn words(&self) -> Vec<&str> {
+        match self {
+            Self::All(filters) | Self::Any(filters) => {
+                filters.iter().flat_map(Self::words).collect()
+            }
+            Self::Text(text) => vec![text],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl DateField {
+    /// The day of this date of the item
+    #[must_use]
+    pub fn get(self, item: &Item) -> Option<NaiveDate> {
+        match self {
+            Self::Due => item.due.map(|x| x.date),
+            Self::Scheduled => item.scheduled.map(|x| x.date),
+            Self::Created => item.created.map(|x| x.date_naive()),
+            Self::Modified => item.modified.map(|x| x.date_naive()),
+        }
+    }
+}
+
+impl Comparison {
+    /// Does a date that compares to the one in the filter like `ordering` match?
+    #[must_use]
+    pub const fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Less => ordering.is_lt(),
+            Self::LessOrEqual => ordering.is_le(),
+            Self::Equal => ordering.is_eq(),
+            Self::GreaterOrEqual => ordering.is_ge(),
+            Self::Greater => ordering.is_gt(),
+        }
+    }
+}
+
+/// Reads a filter. Relative dates like `1w` are relative to `now`
+///
+/// # Errors
+/// Returns a message describing what couldn't be read
+pub fn parse(input: &str, now: NaiveDateTime) -> Result<Filter, String> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        position: 0,
+        now,
+    };
+    if parser.tokens.is_empty() {
+        return Ok(Filter::All(Vec::new()));
+    }
+    let filter = parser.any()?;
+    parser
+        .next()
+        .map_or(Ok(filter), |token| Err(format!("Unexpected {token}")))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    /// A word that may be a term like `tag:work`
+    Word(String),
+    /// Text in quotes, always searched for as is
+    Quoted(String),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open => write!(f, "("),
+            Self::Close => write!(f, ")"),
+            Self::And => write!(f, "AND"),
+            Self::Or => write!(f, "OR"),
+            Self::Not => write!(f, "-"),
+            Self::Word(x) => write!(f, "{x}"),
+            Self::Quoted(x) => write!(f, "\"{x}\""),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '-' => tokens.push(Token::Not),
+            '"' => {
+                let text = chars.by_ref().take_while(|&x| x != '"').collect::<String>();
+                tokens.push(Token::Quoted(text));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+    if input.chars().filter(|&x| x == '"').count() % 2 == 1 {
+        return Err("Missing closing \"".to_string());
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser. `OR` binds weaker than `AND`, which binds weaker than `-`
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    now: NaiveDateTime,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn any(&mut self) -> Result<Filter, String> {
+        let mut filters = vec![self.all()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            filters.push(self.all()?);
+        }
+        Ok(flatten(filters, Filter::Any))
+    }
+
+    fn all(&mut self) -> Result<Filter, String> {
+        let mut filters = vec![self.not()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                }
+                // Terms next to each other have to match too
+                Some(Token::Not | Token::Open | Token::Word(_) | Token::Quoted(_)) => (),
+                _ => break,
+            }
+            filters.push(self.not()?);
+        }
+        Ok(flatten(filters, Filter::All))
+    }
+
+    fn not(&mut self) -> Result<Filter, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Filter::Not(Box::new(self.not()?)));
+        }
+        self.term()
+    }
+
+    fn term(&mut self) -> Result<Filter, String> {
+        match self.next() {
+            Some(Token::Open) => {
+                let filter = self.any()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(filter),
+                    _ => Err("Missing )".to_string()),
+                }
+            }
+            Some(Token::Quoted(text)) => Ok(Filter::Text(text)),
+            Some(Token::Word(word)) => match word.split_once(':') {
+                Some((key, value)) => self.key_value(key, value),
+                None => Ok(Filter::Text(word)),
+            },
+            Some(token) => Err(format!("Unexpected {token}")),
+            None => Err("Unexpected end".to_string()),
+        }
+    }
+
+    fn key_value(&self, key: &str, value: &str) -> Result<Filter, String> {
+        let date_field = match key.to_lowercase().as_str() {
+            "tag" | "list" | "pri" | "priority" | "is" | "due" | "scheduled" | "created"
+            | "modified"
+                if value.is_empty() =>
+            {
+                return Err(format!("Missing value for {key}:"));
+            }
+            "tag" => return Ok(Filter::Tag(value.trim_start_matches('#').to_string())),
+            "list" => return Ok(Filter::List(value.to_string())),
+            "pri" | "priority" => return parse_priority(value).map(Filter::Priority),
+            "is" => return parse_is(value).map(Filter::Is),
+            "due" => DateField::Due,
+            "scheduled" => DateField::Scheduled,
+            "created" => DateField::Created,
+            "modified" => DateField::Modified,
+            // Words like `12:30` or `http://...` are searched for as is
+            _ => return Ok(Filter::Text(format!("{key}:{value}"))),
+        };
+        if value.eq_ignore_ascii_case("none") {
+            return Ok(Filter::NoDate(date_field));
+        }
+        let (comparison, value) = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ]
+        .into_iter()
+        .find_map(|(prefix, comparison)| Some((comparison, value.strip_prefix(prefix)?)))
+        .unwrap_or((Comparison::Equal, value));
+        // `1w` is short for `+1w`
+        let offset;
+        let value = if value.starts_with(|x: char| x.is_ascii_digit()) && !value.contains('-') {
+            offset = format!("+{value}");
+            &offset
+        } else {
+            value
+        };
+        let date = date::parse(value, self.now).map_err(|e| format!("{key}: {e}"))?;
+        Ok(Filter::Date(date_field, comparison, date.date))
+    }
+}
+
+/// A single filter doesn't have to be wrapped
+fn flatten(mut filters: Vec<Filter>, wrap: fn(Vec<Filter>) -> Filter) -> Filter {
+    if filters.len() == 1 {
+        filters.remove(0)
+    } else {
+        wrap(filters)
+    }
+}
+
+fn parse_priority(value: &str) -> Result<Option<Priority>, String> {
+    match value.to_lowercase().as_str() {
+        "a" => Ok(Some(Priority::A)),
+        "b" => Ok(Some(Priority::B)),
+        "c" => Ok(Some(Priority::C)),
+        "d" => Ok(Some(Priority::D)),
+        "none" => Ok(None),
+        _ => Err(format!("Unknown priority {value}")),
+    }
+}
+
+fn parse_is(value: &str) -> Result<Is, String> {
+    match value.to_lowercase().as_str() {
+        "done" => Ok(Is::Done),
+        "todo" => Ok(Is::Todo),
+        "blocked" => Ok(Is::Blocked),
+        "overdue" => Ok(Is::Overdue),
+        "recurring" => Ok(Is::Recurring),
+        _ => Err(format!("Unknown state is:{value}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Days;
+
+    use super::*;
+    use crate::{parse::date::When, testing::now};
+
+    fn read(input: &str) -> Filter {
+        parse(input, now()).unwrap()
+    }
+
+    fn text(x: &str) -> Filter {
+        Filter::Text(x.to_string())
+    }
+
+    fn in_days(days: u64) -> NaiveDate {
+        now().date().checked_add_days(Days::new(days)).unwrap()
+    }
+
+    fn matches(input: &str, item: &Item) -> bool {
+        let context = Context {
+            list: "Home",
+            items: &HashMap::new(),
+            now: now(),
+        };
+        read(input).matches(item, &context)
+    }
+
+    #[test]
+    fn or_binds_weaker_than_and() {
+        assert_eq!(
+            read("a b OR c"),
+            Filter::Any(vec![Filter::All(vec![text("a"), text("b")]), text("c")])
+        );
+        assert_eq!(
+            read("a OR b AND c"),
+            Filter::Any(vec![text("a"), Filter::All(vec![text("b"), text("c")])])
+        );
+        assert_eq!(read("a AND b"), read("a b"));
+    }
+
+    #[test]
+    fn not_binds_strongest() {
+        assert_eq!(
+            read("-a b"),
+            Filter::All(vec![Filter::Not(Box::new(text("a"))), text("b")])
+        );
+        assert_eq!(read("NOT a"), read("-a"));
+        assert_eq!(
+            read("--a"),
+            Filter::Not(Box::new(Filter::Not(Box::new(text("a")))))
+        );
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert_eq!(
+            read("(a OR b) c"),
+            Filter::All(vec![Filter::Any(vec![text("a"), text("b")]), text("c")])
+        );
+        assert_eq!(
+            read("-(a OR b)"),
+            Filter::Not(Box::new(Filter::Any(vec![text("a"), text("b")])))
+        );
+        assert_eq!(read("((a))"), text("a"));
+        assert!(parse("(a", now()).is_err());
+        assert!(parse("a)", now()).is_err());
+        assert!(parse("a OR", now()).is_err());
+    }
+
+    #[test]
+    fn quotes_are_searched_as_is() {
+        assert_eq!(read("\"a OR -tag:b\""), text("a OR -tag:b"));
+        assert_eq!(
+            read("-\"a b\" c"),
+            Filter::All(vec![Filter::Not(Box::new(text("a b"))), text("c")])
+        );
+        assert!(parse("\"a", now()).is_err());
+    }
+
+    #[test]
+    fn empty_input_matches_everything() {
+        assert_eq!(read(""), Filter::All(Vec::new()));
+        assert_eq!(read("  "), Filter::All(Vec::new()));
+        assert!(matches("", &Item::default()));
+    }
+
+    #[test]
+    fn keys_and_values() {
+        assert_eq!(read("tag:#Work"), Filter::Tag("Work".to_string()));
+        assert_eq!(read("list:Home"), Filter::List("Home".to_string()));
+        assert_eq!(read("pri:a"), Filter::Priority(Some(Priority::A)));
+        assert_eq!(read("priority:none"), Filter::Priority(None));
+        assert_eq!(read("IS:Done"), Filter::Is(Is::Done));
+        assert!(parse("pri:e", now()).is_err());
+        assert!(parse("is:maybe", now()).is_err());
+        assert!(parse("tag:", now()).is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_text() {
+        assert_eq!(read("12:30"), text("12:30"));
+        assert_eq!(read("http://example.com"), text("http://example.com"));
+        assert_eq!(read("note:"), text("note:"));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            read("due:<1w"),
+            Filter::Date(DateField::Due, Comparison::Less, in_days(7))
+        );
+        assert_eq!(
+            read("due:<=+2d"),
+            Filter::Date(DateField::Due, Comparison::LessOrEqual, in_days(2))
+        );
+        assert_eq!(
+            read("created:>=2026-01-01"),
+            Filter::Date(
+                DateField::Created,
+                Comparison::GreaterOrEqual,
+                NaiveDate::from_ymd_opt(2026, 1, 1).unwrap()
+            )
+        );
+        assert_eq!(
+            read("scheduled:tomorrow"),
+            Filter::Date(DateField::Scheduled, Comparison::Equal, in_days(1))
+        );
+        assert_eq!(read("due:none"), Filter::NoDate(DateField::Due));
+        assert!(parse("due:<soon", now()).is_err());
+    }
+
+    #[test]
+    fn date_comparisons() {
+        let item = Item {
+            due: Some(When {
+                date: in_days(2),
+                time: None,
+            }),
+            ..Item::default()
+        };
+        assert!(matches("due:<1w", &item));
+        assert!(matches("due:>tomorrow", &item));
+        assert!(matches("due:2d", &item));
+        assert!(matches("due:<=2d", &item));
+        assert!(!matches("due:<2d", &item));
+        assert!(!matches("due:>=1w", &item));
+        assert!(!matches("due:none", &item));
+        assert!(!matches("scheduled:<1w", &item));
+        assert!(matches("scheduled:none", &item));
+        assert!(!matches("is:overdue", &item));
+    }
+
+    #[test]
+    fn matching() {
+        let item = Item {
+            title: "Water the plants".into(),
+            tags: ["home".to_string()].into(),
+            priority: Some(Priority::B),
+            ..Item::default()
+        };
+        assert!(matches("plants tag:HOME", &item));
+        assert!(matches("pri:a OR pri:b", &item));
+        assert!(!matches("plants -tag:home", &item));
+        assert!(matches("list:home is:todo", &item));
+        assert!(!matches("list:work OR is:done", &item));
+    }
+}
@@ -1,23 +1,22 @@
 //! Imports all modules
-#![deny(
-    missing_docs,
-    missing_abi,
-    missing_fragment_specifier,
-    missing_debug_implementations
-)]
+#![deny(missing_docs, missing_abi, missing_debug_implementations)]
 #![warn(clippy::pedantic, clippy::nursery)]
-#![feature(box_into_inner)]
 
-use core::fmt::Debug;
+use core::{cmp::Ordering, fmt::Debug};
 
 pub mod app;
 pub mod errors;
+pub mod filter;
 pub mod help;
+pub mod ordered_list;
 pub mod parse;
 pub mod popup;
-pub mod ui;
-pub mod ordered_list;
 pub mod static_info;
+pub mod ui;
+pub mod undo;
+
+#[cfg(test)]
+mod testing;
 
 #[must_use]
 /// Returns an ordered list how alike it is to
@@ -38,18 +37,169 @@ where
 /// Implements a scoring trait used for ordering the search items
 pub trait Score {
     /// The scoring function it should return None if the
-    /// search item shouldn't be included in the final list
+    /// search item shouldn't be included in the final list.
+    /// Lower scores are better matches
     fn score(&self, query: &str) -> Option<i64>;
+
+    /// How two items are ordered in an [`ordered_list::OrderedList`].
+    /// By default they are ordered by their score for an empty query
+    fn order(&self, other: &Self) -> Ordering {
+        self.score("").cmp(&other.score(""))
+    }
 }
 
-// FIXME: This is horrible code for judging the score of a given word
-// compared to its query
 impl Score for String {
     fn score(&self, query: &str) -> Option<i64> {
-        if self.contains(query) {
-            i64::try_from(self.len()).ok()
+        fuzzy_match(self, query).map(|x| -x.score)
+    }
+}
+
+/// How well a text matched a query
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Match {
+    /// Higher is better
+    pub score: i64,
+    /// The indices of the chars (not bytes) in the text that matched the query
+    pub positions: Vec<usize>,
+}
+
+const SCORE_MATCH: i64 = 1600;
+const BONUS_BOUNDARY: i64 = 800;
+const BONUS_CAMEL: i64 = 700;
+const BONUS_CONSECUTIVE: i64 = 600;
+const PENALTY_GAP_START: i64 = 300;
+const PENALTY_GAP_EXTENSION: i64 = 100;
+
+/// Matches `query` against `text` if all of its chars appear in `text` in the same order.
+///
+/// Matches at the start of words and runs of consecutive chars score higher, gaps
+/// between matched chars score lower. Shorter texts win ties. The match ignores
+/// case unless `query` contains an uppercase char
+#[must_use]
+pub fn fuzzy_match(text: &str, query: &str) -> Option<Match> {
+    let text = text.chars().collect::<Vec<_>>();
+    let query = query.chars().collect::<Vec<_>>();
+    let case_sensitive = query.iter().any(|x| x.is_uppercase());
+    let eq = |a: char, b: char| {
+        if case_sensitive {
+            a == b
         } else {
-            None
+            a.to_lowercase().eq(b.to_lowercase())
+        }
+    };
+    // The length only matters for ties
+    let length_penalty = i64::try_from(text.len() - query.len().min(text.len())).ok()?;
+    if query.is_empty() {
+        return Some(Match {
+            score: -length_penalty,
+            positions: Vec::new(),
+        });
+    }
+    let bonus = |j: usize| {
+        let current = text[j];
+        match j.checked_sub(1).map(|x| text[x]) {
+            None => BONUS_BOUNDARY,
+            Some(previous) if !previous.is_alphanumeric() && current.is_alphanumeric() => {
+                BONUS_BOUNDARY
+            }
+            Some(previous) if previous.is_lowercase() && current.is_uppercase() => BONUS_CAMEL,
+            Some(_) => 0,
+        }
+    };
+
+    // best[i][j] is the best score for matching query[..=i] with query[i] at text[j].
+    // from[i][j] is where query[i - 1] was matched for that score
+    let mut best = vec![vec![None::<i64>; text.len()]; query.len()];
+    let mut from = vec![vec![0; text.len()]; query.len()];
+    for (i, &q) in query.iter().enumerate() {
+        // The best way to have matched query[i - 1] before a gap that ends right before j
+        let mut gap = None::<(i64, usize)>;
+        for j in 0..text.len() {
+            if i > 0 && j >= 2 {
+                let extended = gap.map(|(score, k)| (score - PENALTY_GAP_EXTENSION, k));
+                let started = best[i - 1][j - 2].map(|x| (x - PENALTY_GAP_START, j - 2));
+                gap = better(extended, started);
+            }
+            if !eq(text[j], q) {
+                continue;
+            }
+            let previous = if i == 0 {
+                Some((0, 0))
+            } else {
+                let consecutive = j
+                    .checked_sub(1)
+                    .and_then(|k| best[i - 1][k].map(|x| (x + BONUS_CONSECUTIVE, k)));
+                better(gap, consecutive)
+            };
+            if let Some((score, k)) = previous {
+                best[i][j] = Some(score + SCORE_MATCH + bonus(j));
+                from[i][j] = k;
+            }
         }
     }
+
+    let last = query.len() - 1;
+    let (score, mut j) = (0..text.len())
+        .filter_map(|j| best[last][j].map(|x| (x, j)))
+        .max_by_key(|&(score, j)| (score, std::cmp::Reverse(j)))?;
+    let mut positions = vec![0; query.len()];
+    for i in (0..query.len()).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+    Some(Match {
+        score: score - length_penalty,
+        positions,
+    })
+}
+
+/// The one with the higher score, `b` if they are the same
+fn better(a: Option<(i64, usize)>, b: Option<(i64, usize)>) -> Option<(i64, usize)> {
+    a.into_iter().chain(b).max_by_key(|x| x.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(text: &str, query: &str) -> Option<Vec<usize>> {
+        fuzzy_match(text, query).map(|x| x.positions)
+    }
+
+    #[test]
+    fn matches_chars_in_order() {
+        assert_eq!(positions("foo bar", "fb"), Some(vec![0, 4]));
+        assert_eq!(positions("foo bar", "bf"), None);
+        assert_eq!(positions("foo bar", "fooo"), None);
+        assert_eq!(positions("foo bar", ""), Some(Vec::new()));
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(fuzzy_match("Hello", "hello").is_some());
+        assert!(fuzzy_match("hello", "Hello").is_none());
+        assert!(fuzzy_match("Hello", "He").is_some());
+    }
+
+    #[test]
+    fn prefers_word_starts_and_runs() {
+        assert_eq!(positions("abar bar", "bar"), Some(vec![5, 6, 7]));
+        assert_eq!(positions("fooBar", "b"), Some(vec![3]));
+        assert_eq!(positions("a_b_c abc", "abc"), Some(vec![6, 7, 8]));
+        // The first of two equally good matches
+        assert_eq!(positions("xbc xbc", "bc"), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn positions_count_chars() {
+        assert_eq!(positions("äöü b", "b"), Some(vec![4]));
+    }
+
+    #[test]
+    fn shorter_texts_win_ties() {
+        let short = fuzzy_match("todo", "todo").unwrap().score;
+        let long = fuzzy_match("todos", "todo").unwrap().score;
+        assert!(short > long);
+        assert!(fuzzy_match("a", "a").unwrap().score > fuzzy_match("b a", "a").unwrap().score);
+    }
 }
@@ -1,6 +1,6 @@
 //! Crate to manage something. Haven't decided yet
 
-use std::io;
+use std::{env, io, time::Duration};
 use todo::{app::App, errors, ui::ui};
 
 use crossterm::{
@@ -25,6 +25,17 @@ fn main() -> color_eyre::Result<()> {
 
     // create app and run it
     let mut app = App::from_files("./lists.json", "./help.json").unwrap();
+    // TODO_AUTOSAVE is the idle time in seconds before autosaving. 0 disables it
+    if let Some(secs) = env::var("TODO_AUTOSAVE")
+        .ok()
+        .and_then(|x| x.parse::<u64>().ok())
+    {
+        app.autosave = (secs != 0).then(|| Duration::from_secs(secs));
+    }
+    // TODO_CONFIRM_DELETE=0 deletes items with subtasks without asking first
+    if let Ok(confirm) = env::var("TODO_CONFIRM_DELETE") {
+        app.confirm_deletes = confirm != "0";
+    }
     let res = run_app(&mut terminal, &mut app);
 
     // restore terminal
@@ -44,6 +55,9 @@ fn main() -> color_eyre::Result<()> {
     Ok(())
 }
 
+/// How often the app checks whether it should autosave
+const TICK_RATE: Duration = Duration::from_millis(250);
+
 fn run_app<B>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<bool>
 where
     B: Backend,
@@ -51,12 +65,17 @@ where
     loop {
         terminal.draw(|f| ui(f, app))?;
 
+        // Wake up regularly so that autosaving happens even when idle
+        if !event::poll(TICK_RATE)? {
+            app.tick();
+            continue;
+        }
         if let Event::Key(key) = event::read()? {
             if key.kind == event::KeyEventKind::Release {
                 // Skip events that are not KeyEventKind::Press
                 continue;
             }
-            if let Some(x) = app.handle_input(key.code) {
+            if let Some(x) = app.handle_key(key) {
                 return Ok(x);
             };
         }
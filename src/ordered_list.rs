@@ -1,17 +1,57 @@
 //! A data structure that keeps an ordered list based off the Score trait
 use std::{
+    cmp::Ordering,
     fmt::Debug,
     ops::{Deref, DerefMut},
 };
 
 use crate::Score;
 
+/// How an [`OrderedList`] keeps its values ordered
+pub enum Order<T> {
+    /// Using [`Score::order`]
+    Score,
+    /// Using a custom comparison
+    By(fn(&T, &T) -> Ordering),
+}
+
+impl<T> Order<T>
+where
+    T: Score,
+{
+    /// Compares two values in this order
+    pub fn compare(&self, a: &T, b: &T) -> Ordering {
+        match self {
+            Self::Score => a.order(b),
+            Self::By(f) => f(a, b),
+        }
+    }
+}
+
+impl<T> Clone for Order<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Order<T> {}
+
+impl<T> Debug for Order<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Score => write!(f, "Score"),
+            Self::By(_) => write!(f, "By"),
+        }
+    }
+}
+
 /// The main struct of this module
 pub struct OrderedList<T>
 where
     T: Debug + Score,
 {
     data: Vec<T>,
+    order: Order<T>,
 }
 
 impl<T> Clone for OrderedList<T>
@@ -21,6 +61,7 @@ where
     fn clone(&self) -> Self {
         Self {
             data: self.data.clone(),
+            order: self.order,
         }
     }
 }
@@ -32,6 +73,7 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("OrderedList")
             .field("data", &self.data)
+            .field("order", &self.order)
             .finish()
     }
 }
@@ -43,6 +85,7 @@ where
     fn default() -> Self {
         Self {
             data: Vec::default(),
+            order: Order::Score,
         }
     }
 }
@@ -56,8 +99,11 @@ where
             .into_iter()
             .filter(|x| x.score("").is_some())
             .collect::<Vec<_>>();
-        data.sort_by_cached_key(|x| x.score("").unwrap());
-        Self { data }
+        data.sort_by(T::order);
+        Self {
+            data,
+            order: Order::Score,
+        }
     }
 }
 
@@ -65,16 +111,42 @@ impl<T> OrderedList<T>
 where
     T: Debug + Score,
 {
-    /// Adds a value to the orderedlist keeping it ordered
+    /// Adds a value to the orderedlist keeping it ordered.
+    /// It goes after every value it's equal to
     pub fn insert(&mut self, data: T) -> usize {
         let pos = self
             .data
-            .iter()
-            .position(|x| x.score("") < data.score(""))
-            .unwrap_or(self.data.len());
+            .partition_point(|x| self.order.compare(x, &data).is_le());
         self.data.insert(pos, data);
         pos
     }
+
+    /// Is the value at `index` ordered correctly relative to the values next to it?
+    #[must_use]
+    pub fn is_in_order(&self, index: usize) -> bool {
+        let value = &self.data[index];
+        index
+            .checked_sub(1)
+            .is_none_or(|x| self.order.compare(&self.data[x], value).is_le())
+            && self
+                .data
+                .get(index + 1)
+                .is_none_or(|x| self.order.compare(value, x).is_le())
+    }
+
+    /// Creates a list that orders its values in this order
+    #[must_use]
+    pub fn with_order(order: Order<T>, mut data: Vec<T>) -> Self {
+        data.sort_by(|a, b| order.compare(a, b));
+        Self { data, order }
+    }
+
+    /// Changes how the values are ordered and reorders them.
+    /// Values that are equal keep the order they had
+    pub fn set_order(&mut self, order: Order<T>) {
+        self.order = order;
+        self.data.sort_by(|a, b| order.compare(a, b));
+    }
 }
 
 impl<T> Deref for OrderedList<T>
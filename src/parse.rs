@@ -2,23 +2,480 @@
 //! and returning usable data
 /// Defines wrappers for handling todo items
 pub mod todo {
-    use std::fmt::Debug;
+    use std::cmp::Ordering;
+    use std::collections::{BTreeSet, HashMap};
+    use std::fmt::{self, Debug, Display};
     use std::ops::{Index, IndexMut};
 
-    use crate::{ordered_list::OrderedList, Score};
+    use chrono::{DateTime, Local};
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
 
-    use super::ListItem;
+    use crate::{
+        fuzzy_match,
+        ordered_list::{Order, OrderedList},
+        Match, Score,
+    };
+
+    use super::{
+        date::{Repeat, When},
+        ListItem,
+    };
 
     /// A single Todo-item
-    #[derive(Debug, Default, Clone)]
+    #[derive(Debug, Default, Clone, PartialEq, Eq)]
     pub struct Item {
+        /// What the item is referred to by. Never changes, even when the item moves
+        pub id: Id,
         /// The title of this todo item
         pub title: Box<str>,
         /// A description of what this todo item entails
         pub description: Box<str>,
         /// How far one has scrolled in the description
         pub description_scroll: usize,
-        // TODO: Add a tag system
+        /// Tags used to group items together
+        pub tags: BTreeSet<String>,
+        /// When the item was marked as done. `None` if it is still to be done
+        pub completed: Option<DateTime<Local>>,
+        /// When the item has to be done by
+        pub due: Option<When>,
+        /// When work on the item is planned to start
+        pub scheduled: Option<When>,
+        /// How important the item is
+        pub priority: Option<Priority>,
+        /// When the item was added
+        pub created: Option<DateTime<Local>>,
+        /// When the item was last changed
+        pub modified: Option<DateTime<Local>>,
+        /// Every change made to the item, oldest first. Only ever appended to
+        pub history: Vec<Change>,
+        /// The item this is a subtask of
+        pub parent: Option<Id>,
+        /// Are the subtasks of this item hidden?
+        pub collapsed: bool,
+        /// The items that have to be done before this one can be worked on.
+        /// They may be in other lists
+        pub blocked_by: BTreeSet<Id>,
+        /// How often the item comes back after it has been done
+        pub repeat: Option<Repeat>,
+        /// The occurrence that was created when this repeating item was done,
+        /// see [`Self::next_occurrence`]
+        pub next: Option<Id>,
+        /// Where the item goes when its list is sorted manually. Lower comes first
+        pub rank: usize,
+    }
+
+    /// A part of an item whose changes are kept in its history
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    #[allow(missing_docs)]
+    pub enum Field {
+        Title,
+        Description,
+        Tags,
+        Status,
+        Due,
+        Scheduled,
+        Priority,
+        Repeat,
+    }
+
+    /// A single change made to an item
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Change {
+        /// When the change was made
+        pub at: DateTime<Local>,
+        /// What was changed
+        pub field: Field,
+        /// The value before the change
+        pub old: String,
+        /// The value after the change
+        pub new: String,
+    }
+
+    /// A unique identifier of a todo item. A new one is generated by [`Default`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct Id(Uuid);
+
+    /// Every item of every list by its id, together with the name of the list it is in
+    pub type Lookup<'a> = HashMap<Id, (&'a str, &'a Item)>;
+
+    impl Default for Id {
+        fn default() -> Self {
+            Self(Uuid::new_v4())
+        }
+    }
+
+    /// How important an item is. `A` is the most important
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    #[allow(missing_docs)]
+    pub enum Priority {
+        A,
+        B,
+        C,
+        D,
+    }
+
+    impl Priority {
+        /// The next priority when cycling through them. Going past
+        /// the lowest priority removes it
+        #[must_use]
+        pub const fn cycle(priority: Option<Self>) -> Option<Self> {
+            match priority {
+                None => Some(Self::A),
+                Some(Self::A) => Some(Self::B),
+                Some(Self::B) => Some(Self::C),
+                Some(Self::C) => Some(Self::D),
+                Some(Self::D) => None,
+            }
+        }
+    }
+
+    /// The ways the items of a list can be sorted
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum SortMode {
+        /// In the order the user put them in
+        Manual,
+        /// Most important first
+        #[default]
+        Priority,
+        /// Soonest due first
+        Due,
+        /// Oldest first
+        Created,
+        /// By title
+        Alphabetical,
+        /// Items that are still to be done first
+        Completion,
+    }
+
+    impl SortMode {
+        /// The next mode when cycling through them
+        #[must_use]
+        pub const fn next(self) -> Self {
+            match self {
+                Self::Manual => Self::Priority,
+                Self::Priority => Self::Due,
+                Self::Due => Self::Created,
+                Self::Created => Self::Alphabetical,
+                Self::Alphabetical => Self::Completion,
+                Self::Completion => Self::Manual,
+            }
+        }
+
+        /// How an [`OrderedList`] has to order items to be sorted like this
+        #[must_use]
+        pub fn order(self) -> Order<Item> {
+            match self {
+                Self::Manual => Order::By(|a, b| a.rank.cmp(&b.rank)),
+                Self::Priority => Order::Score,
+                Self::Due => Order::By(|a, b| {
+                    // Items without a due date go last
+                    (a.due.is_none(), a.due)
+                        .cmp(&(b.due.is_none(), b.due))
+                        .then_with(|| a.order(b))
+                }),
+                Self::Created => Order::By(|a, b| a.created.cmp(&b.created)),
+                Self::Alphabetical => {
+                    Order::By(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+                }
+                Self::Completion => Order::By(|a, b| {
+                    (a.is_done(), a.completed)
+                        .cmp(&(b.is_done(), b.completed))
+                        .then_with(|| a.order(b))
+                }),
+            }
+        }
+    }
+
+    impl Display for SortMode {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", format!("{self:?}").to_lowercase())
+        }
+    }
+
+    impl Display for Field {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", format!("{self:?}").to_lowercase())
+        }
+    }
+
+    impl Display for Priority {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{self:?}")
+        }
+    }
+
+    impl Item {
+        /// Has this item been done?
+        #[must_use]
+        pub const fn is_done(&self) -> bool {
+            self.completed.is_some()
+        }
+
+        /// "done" or "todo"
+        #[must_use]
+        pub const fn status(&self) -> &'static str {
+            if self.is_done() {
+                "done"
+            } else {
+                "todo"
+            }
+        }
+
+        /// Marks the item as done, or as not done if it already was
+        pub fn toggle_done(&mut self) {
+            let old = self.status();
+            self.completed = match self.completed {
+                Some(_) => None,
+                None => Some(Local::now()),
+            };
+            self.record(Field::Status, old.to_string(), self.status().to_string());
+        }
+
+        /// Moves on to the next priority, see [`Priority::cycle`]
+        pub fn cycle_priority(&mut self) {
+            let old = self.priority;
+            self.priority = Priority::cycle(self.priority);
+            self.record(Field::Priority, show(old), show(self.priority));
+        }
+
+        /// Copies over everything that can be changed in the edit popup
+        pub fn update(&mut self, edited: Self) {
+            self.record(
+                Field::Title,
+                self.title.to_string(),
+                edited.title.to_string(),
+            );
+            self.record(
+                Field::Description,
+                self.description.to_string(),
+                edited.description.to_string(),
+            );
+            self.record(
+                Field::Tags,
+                format_tags(&self.tags),
+                format_tags(&edited.tags),
+            );
+            self.record(Field::Due, show(self.due), show(edited.due));
+            self.record(
+                Field::Scheduled,
+                show(self.scheduled),
+                show(edited.scheduled),
+            );
+            self.record(
+                Field::Repeat,
+                show(self.repeat.as_ref()),
+                show(edited.repeat.as_ref()),
+            );
+            self.title = edited.title;
+            self.description = edited.description;
+            self.tags = edited.tags;
+            self.due = edited.due;
+            self.scheduled = edited.scheduled;
+            self.repeat = edited.repeat;
+        }
+
+        /// Creates the next occurrence of a repeating item, moving its dates forward.
+        /// The repetition moves over to the new item so it only happens once,
+        /// see [`Self::take_back`]
+        pub fn next_occurrence(&mut self, now: DateTime<Local>) -> Option<Self> {
+            let repeat = self.repeat.as_ref()?;
+            let today = now.date_naive();
+            let (due, scheduled) = match (self.due, self.scheduled) {
+                // Scheduled stays the same amount of time ahead of the due date
+                (Some(due), scheduled) => {
+                    let date = repeat.next(Some(due.date), today)?;
+                    let scheduled = scheduled.and_then(|x| {
+                        Some(When {
+                            date: x
+                                .date
+                                .checked_add_signed(date.signed_duration_since(due.date))?,
+                            ..x
+                        })
+                    });
+                    (Some(When { date, ..due }), scheduled)
+                }
+                (None, Some(scheduled)) => {
+                    let date = repeat.next(Some(scheduled.date), today)?;
+                    (None, Some(When { date, ..scheduled }))
+                }
+                (None, None) => {
+                    let date = repeat.next(None, today)?;
+                    (Some(When { date, time: None }), None)
+                }
+            };
+            let next = Self {
+                id: Id::default(),
+                description_scroll: 0,
+                completed: None,
+                due,
+                scheduled,
+                created: Some(now),
+                modified: None,
+                history: Vec::new(),
+                // The blockers were done before this one
+                blocked_by: BTreeSet::new(),
+                repeat: self.repeat.take(),
+                next: None,
+                ..self.clone()
+            };
+            self.next = Some(next.id);
+            Some(next)
+        }
+
+        /// Undoes [`Self::next_occurrence`] once this item isn't done anymore,
+        /// taking the repetition back from `next`, the occurrence it created
+        pub fn take_back(&mut self, next: Self) {
+            self.repeat = next.repeat;
+            self.next = None;
+        }
+
+        /// Is any of the items blocking this one still to be done?
+        /// Items that can't be found don't block anything
+        #[must_use]
+        pub fn is_blocked(&self, items: &Lookup) -> bool {
+            !self.is_done()
+                && self
+                    .blocked_by
+                    .iter()
+                    .any(|x| items.get(x).is_some_and(|(_, x)| !x.is_done()))
+        }
+
+        /// The items that are waiting on this one
+        #[must_use]
+        pub fn dependents<'a>(&self, items: &Lookup<'a>) -> Vec<(&'a str, &'a Self)> {
+            items
+                .values()
+                .filter(|(_, x)| x.blocked_by.contains(&self.id))
+                .copied()
+                .collect()
+        }
+
+        /// The searchable part of the item that matches `query` best and how.
+        /// Matches in the title count the most, matches in the description the least.
+        /// Tags are matched the way [`format_tags`] shows them
+        #[must_use]
+        pub fn best_match(&self, query: &str) -> Option<(Field, Match)> {
+            // Ties go to the last one
+            [
+                (Field::Description, fuzzy_match(&self.description, query)),
+                (Field::Tags, fuzzy_match(&format_tags(&self.tags), query)),
+                (Field::Title, fuzzy_match(&self.title, query)),
+            ]
+            .into_iter()
+            .filter_map(|(field, x)| Some((field, x?)))
+            .max_by_key(|(field, x)| weighted(*field, x))
+        }
+
+        /// Adds a change to the history, unless nothing actually changed
+        fn record(&mut self, field: Field, old: String, new: String) {
+            if old == new {
+                return;
+            }
+            let at = Local::now();
+            self.modified = Some(at);
+            self.history.push(Change {
+                at,
+                field,
+                old,
+                new,
+            });
+        }
+    }
+
+    /// Removes the item at `index` and its subtasks, their subtasks and so on
+    /// from `items`. The item itself comes first
+    fn take_tree(items: &mut Vec<Item>, index: usize) -> Vec<Item> {
+        if index >= items.len() {
+            return Vec::new();
+        }
+        let mut taken = vec![items.remove(index)];
+        let mut i = 0;
+        while let Some(id) = taken.get(i).map(|x| x.id) {
+            let (children, left) = std::mem::take(items)
+                .into_iter()
+                .partition::<Vec<_>, _>(|x| x.parent == Some(id));
+            *items = left;
+            taken.extend(children);
+            i += 1;
+        }
+        taken
+    }
+
+    /// Gives the items new ids so that they can be told apart from the ones they
+    /// were copied from. Subtasks and blockers among them follow along
+    pub fn renew_ids<'a>(items: impl IntoIterator<Item = &'a mut Item>) {
+        let mut items = items.into_iter().collect::<Vec<_>>();
+        let ids = items
+            .iter()
+            .map(|x| (x.id, Id::default()))
+            .collect::<HashMap<_, _>>();
+        let renew = |x: Id| ids.get(&x).copied().unwrap_or(x);
+        for item in &mut items {
+            item.id = renew(item.id);
+            item.parent = item.parent.map(renew);
+            item.blocked_by = item.blocked_by.iter().copied().map(renew).collect();
+            item.next = item.next.map(renew);
+        }
+    }
+
+    /// Does the item `item` have to wait on `other`, either directly or through
+    /// the items blocking it? Every item waits on itself
+    #[must_use]
+    pub fn waits_on(items: &Lookup, item: Id, other: Id) -> bool {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![item];
+        while let Some(id) = stack.pop() {
+            if id == other {
+                return true;
+            }
+            if seen.insert(id) {
+                if let Some((_, x)) = items.get(&id) {
+                    stack.extend(&x.blocked_by);
+                }
+            }
+        }
+        false
+    }
+
+    /// How much a match in this part of an item counts when searching.
+    /// Long texts can score below 0 because of their length, which a bigger
+    /// weight would only make worse
+    fn weighted(field: Field, x: &Match) -> i64 {
+        let weight = match field {
+            Field::Title => 3,
+            Field::Tags => 2,
+            _ => 1,
+        };
+        x.score.max(0) * weight
+    }
+
+    /// How an optional value is shown in the history
+    fn show<T: Display>(value: Option<T>) -> String {
+        value.map(|x| x.to_string()).unwrap_or_default()
+    }
+
+    /// Splits a string into tags. Tags are separated by whitespace or commas and
+    /// may be prefixed by a `#`
+    #[must_use]
+    pub fn parse_tags(tags: &str) -> BTreeSet<String> {
+        tags.split(|x: char| x.is_whitespace() || x == ',')
+            .map(|x| x.trim_start_matches('#'))
+            .filter(|x| !x.is_empty())
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// Formats tags the way [`parse_tags`] reads them
+    #[must_use]
+    pub fn format_tags(tags: &BTreeSet<String>) -> String {
+        tags.iter()
+            .map(|x| format!("#{x}"))
+            .collect::<Vec<_>>()
+            .join(" ")
     }
 
     impl ListItem for Item {
@@ -40,6 +497,12 @@ pub mod todo {
         /// TODO: This shouln't be public
         /// look at how Box does its thing
         pub items: OrderedList<T>,
+        /// Items that have been put away, but not thrown out
+        pub archive: Vec<T>,
+        /// Items that have been deleted, kept until they are purged
+        pub trash: Vec<T>,
+        /// How the items are sorted
+        pub sort: SortMode,
     }
 
     impl<T> Items<T>
@@ -52,10 +515,6 @@ pub mod todo {
             self.items.len()
         }
 
-        /// Adds an item
-        pub fn add(&mut self, item: T) {
-            self.items.insert(item);
-        }
         /// Removes an item
         pub fn remove(&mut self, index: usize) -> T {
             self.items.remove(index)
@@ -73,6 +532,236 @@ pub mod todo {
         }
     }
 
+    impl Items<Item> {
+        /// Adds an item and returns where it ended up. It goes after every
+        /// other item in the manual order
+        pub fn add(&mut self, mut item: Item) -> usize {
+            item.rank = self.items.iter().map(|x| x.rank + 1).max().unwrap_or(0);
+            self.items.insert(item)
+        }
+
+        /// The index of the item with this id
+        #[must_use]
+        pub fn position(&self, id: Id) -> Option<usize> {
+            self.items.iter().position(|x| x.id == id)
+        }
+
+        /// Every tag used by any item in alphabetical order
+        #[must_use]
+        pub fn tags(&self) -> BTreeSet<String> {
+            self.items
+                .iter()
+                .flat_map(|x| x.tags.iter().cloned())
+                .collect()
+        }
+
+        /// The indices of the subtasks of every item in the order they are in.
+        /// Top level items are under `None`
+        #[must_use]
+        pub fn tree(&self) -> HashMap<Option<Id>, Vec<usize>> {
+            let mut tree = HashMap::<_, Vec<_>>::new();
+            for (i, item) in self.items.iter().enumerate() {
+                tree.entry(item.parent).or_default().push(i);
+            }
+            tree
+        }
+
+        /// The indices of the subtasks of the item at `index`, their subtasks and so on
+        #[must_use]
+        pub fn descendants(&self, index: usize) -> Vec<usize> {
+            self.descendants_until(index, |_| false)
+        }
+
+        /// Like [`Self::descendants`], but leaves out the subtasks for which
+        /// `stop` is true together with their own subtasks
+        #[must_use]
+        pub fn descendants_until(&self, index: usize, stop: impl Fn(&Item) -> bool) -> Vec<usize> {
+            let tree = self.tree();
+            let mut found = Vec::new();
+            let mut stack = vec![self.items[index].id];
+            while let Some(id) = stack.pop() {
+                for &x in tree.get(&Some(id)).into_iter().flatten() {
+                    if !stop(&self.items[x]) {
+                        found.push(x);
+                        stack.push(self.items[x].id);
+                    }
+                }
+            }
+            found
+        }
+
+        /// How many parents the item at `index` has
+        #[must_use]
+        pub fn depth(&self, index: usize) -> usize {
+            let mut depth = 0;
+            let mut parent = self.items[index].parent;
+            while let Some(x) = parent.and_then(|x| self.position(x)) {
+                depth += 1;
+                parent = self.items[x].parent;
+            }
+            depth
+        }
+
+        /// How many of the direct subtasks of the item at `index` are done and
+        /// how many there are. `tree` is made by [`Self::tree`] so it can be
+        /// shared when this is needed for many items
+        #[must_use]
+        pub fn progress(
+            &self,
+            tree: &HashMap<Option<Id>, Vec<usize>>,
+            index: usize,
+        ) -> (usize, usize) {
+            tree.get(&Some(self.items[index].id))
+                .into_iter()
+                .flatten()
+                .fold((0, 0), |(done, all), &x| {
+                    (done + usize::from(self.items[x].is_done()), all + 1)
+                })
+        }
+
+        /// Copies of the item at `index` and its subtasks with new ids, the copy of
+        /// the item itself first. See [`renew_ids`]
+        #[must_use]
+        pub fn copy_tree(&self, index: usize) -> Vec<Item> {
+            let mut copies = std::iter::once(index)
+                .chain(self.descendants(index))
+                .map(|x| self.items[x].clone())
+                .collect::<Vec<_>>();
+            renew_ids(&mut copies);
+            copies
+        }
+
+        /// Removes the item at `index` together with all its subtasks
+        pub fn remove_tree(&mut self, index: usize) -> Vec<Item> {
+            self.remove_tree_until(index, |_| false)
+        }
+
+        /// Like [`Self::remove_tree`], but leaves the subtasks for which `stop`
+        /// is true where they are, see [`Self::descendants_until`]
+        pub fn remove_tree_until(
+            &mut self,
+            index: usize,
+            stop: impl Fn(&Item) -> bool,
+        ) -> Vec<Item> {
+            let mut indices = self.descendants_until(index, stop);
+            indices.push(index);
+            let (removed, left) = std::mem::take(&mut *self.items)
+                .into_iter()
+                .enumerate()
+                .partition::<Vec<_>, _>(|(i, _)| indices.contains(i));
+            *self.items = left.into_iter().map(|(_, x)| x).collect();
+            removed.into_iter().map(|(_, x)| x).collect()
+        }
+
+        /// Moves the item at `index` and its subtasks into the trash.
+        /// Returns how many items were deleted
+        pub fn delete(&mut self, index: usize) -> usize {
+            let deleted = self.remove_tree(index);
+            let amount = deleted.len();
+            self.trash.extend(deleted);
+            amount
+        }
+
+        /// Moves every item that has been done into the archive, subtasks included.
+        /// Returns how many items were moved
+        pub fn archive_done(&mut self) -> usize {
+            let mut indices = BTreeSet::new();
+            for (i, item) in self.items.iter().enumerate() {
+                if item.is_done() {
+                    indices.insert(i);
+                    indices.extend(self.descendants(i));
+                }
+            }
+            let (done, left) = std::mem::take(&mut *self.items)
+                .into_iter()
+                .enumerate()
+                .partition::<Vec<_>, _>(|(i, _)| indices.contains(i));
+            *self.items = left.into_iter().map(|(_, x)| x).collect();
+            self.archive.extend(done.into_iter().map(|(_, x)| x));
+            indices.len()
+        }
+
+        /// Moves an item and the subtasks archived with it out of the archive
+        /// and back into the list
+        pub fn restore(&mut self, index: usize) {
+            let restored = take_tree(&mut self.archive, index);
+            self.put_back(restored);
+        }
+
+        /// Moves an item and the subtasks deleted with it out of the trash and
+        /// back into the list
+        pub fn restore_deleted(&mut self, index: usize) {
+            let restored = take_tree(&mut self.trash, index);
+            self.put_back(restored);
+        }
+
+        /// Throws an item and the subtasks deleted with it out of the trash for
+        /// good. Returns how many items were purged
+        pub fn purge(&mut self, index: usize) -> usize {
+            take_tree(&mut self.trash, index).len()
+        }
+
+        /// Adds items taken out of the archive or the trash, the first one
+        /// being the parent of the others
+        fn put_back(&mut self, mut restored: Vec<Item>) {
+            if restored.is_empty() {
+                return;
+            }
+            // The parent might not be in the list anymore
+            if restored[0]
+                .parent
+                .is_some_and(|x| self.position(x).is_none())
+            {
+                restored[0].parent = None;
+            }
+            for item in restored {
+                self.add(item);
+            }
+        }
+
+        /// Changes how the items are sorted and sorts them
+        pub fn set_sort(&mut self, sort: SortMode) {
+            self.sort = sort;
+            self.items.set_order(sort.order());
+        }
+
+        /// Swaps two items. The list is ordered manually from then on so that
+        /// they stay where they were put, starting from the order they are in now
+        pub fn swap(&mut self, a: usize, b: usize) {
+            if self.sort != SortMode::Manual {
+                for (i, item) in self.items.iter_mut().enumerate() {
+                    item.rank = i;
+                }
+                self.set_sort(SortMode::Manual);
+            }
+            let rank = self.items[a].rank;
+            self.items[a].rank = self.items[b].rank;
+            self.items[b].rank = rank;
+            self.items.swap(a, b);
+        }
+
+        /// Changes an item in a way that might change where it belongs.
+        /// Returns where it ended up. Items that are still in order, like
+        /// manually ordered ones, stay where they are
+        pub fn modify(&mut self, index: usize, f: impl FnOnce(&mut Item)) -> usize {
+            f(&mut self.items[index]);
+            if self.items.is_in_order(index) {
+                return index;
+            }
+            let item = self.remove(index);
+            self.items.insert(item)
+        }
+
+        /// The tag that alphabetically comes after `current`.
+        /// `None` comes before the first tag and after the last one
+        #[must_use]
+        pub fn next_tag(&self, current: Option<&str>) -> Option<String> {
+            self.tags()
+                .into_iter()
+                .find(|x| current.is_none_or(|current| x.as_str() > current))
+        }
+    }
+
     impl<T> Index<usize> for Items<T>
     where
         T: Debug + Score + ListItem,
@@ -103,6 +792,9 @@ pub mod todo {
         {
             Self {
                 items: iter.into_iter().collect(),
+                archive: Vec::new(),
+                trash: Vec::new(),
+                sort: SortMode::default(),
             }
         }
     }
@@ -113,22 +805,95 @@ pub mod todo {
                 title,
                 description,
                 description_scroll,
+                ..Default::default()
             }
         }
     }
 
     impl Score for Item {
+        /// Items with a priority come first, the most important ones first.
+        /// Items with the same priority are ordered by title
+        fn order(&self, other: &Self) -> Ordering {
+            match (self.priority, other.priority) {
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (a, b) => a.cmp(&b),
+            }
+            .then_with(|| self.title.to_lowercase().cmp(&other.title.to_lowercase()))
+        }
+
+        /// The best match in the title, the tags or the description
         fn score(&self, query: &str) -> Option<i64> {
-            if (self.title.to_string() + &self.description_scroll.to_string()).contains(query) {
-                Some(
-                    (self.title.len() + self.description.len())
-                        .try_into()
-                        .unwrap(),
-                )
-            } else {
-                None
+            self.best_match(query)
+                .map(|(field, x)| -weighted(field, &x))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use chrono::NaiveDate;
+
+        use super::*;
+        use crate::testing::local_now;
+
+        fn on(y: i32, m: u32, d: u32) -> When {
+            When {
+                date: NaiveDate::from_ymd_opt(y, m, d).unwrap(),
+                time: None,
             }
         }
+
+        fn repeating(due: Option<When>, scheduled: Option<When>) -> Item {
+            Item {
+                title: "Water the plants".into(),
+                due,
+                scheduled,
+                blocked_by: BTreeSet::from([Id::default()]),
+                repeat: Some(Repeat::Daily),
+                ..Item::default()
+            }
+        }
+
+        #[test]
+        fn next_occurrence_moves_the_dates() {
+            let mut item = repeating(Some(on(2026, 10, 18)), Some(on(2026, 10, 16)));
+            let next = item.next_occurrence(local_now()).unwrap();
+            assert_eq!(next.due, Some(on(2026, 10, 19)));
+            assert_eq!(next.scheduled, Some(on(2026, 10, 17)));
+            assert!(next.blocked_by.is_empty());
+            assert_eq!(next.repeat, Some(Repeat::Daily));
+            assert_eq!(item.repeat, None);
+            assert_eq!(item.next, Some(next.id));
+        }
+
+        #[test]
+        fn next_occurrence_without_a_due_date() {
+            let mut item = repeating(None, Some(on(2026, 10, 20)));
+            let next = item.next_occurrence(local_now()).unwrap();
+            assert_eq!(next.due, None);
+            assert_eq!(next.scheduled, Some(on(2026, 10, 21)));
+        }
+
+        #[test]
+        fn long_titles_still_beat_descriptions() {
+            let item = Item {
+                title: format!("plants {}", "x".repeat(3000)).into(),
+                description: format!("plants {}", "x".repeat(3100)).into(),
+                ..Item::default()
+            };
+            assert!(fuzzy_match(&item.title, "p").unwrap().score < 0);
+            assert_eq!(item.best_match("p").unwrap().0, Field::Title);
+        }
+
+        #[test]
+        fn taking_back_an_occurrence() {
+            let mut item = repeating(None, None);
+            let next = item.next_occurrence(local_now()).unwrap();
+            assert_eq!(next.due, Some(on(2026, 10, 19)));
+            item.take_back(next);
+            assert_eq!(item.repeat, Some(Repeat::Daily));
+            assert_eq!(item.next, None);
+        }
     }
 }
 
@@ -139,3 +904,416 @@ pub trait ListItem {
     /// The description of the item. This gets shown when the title is selected
     fn description(&self) -> String;
 }
+
+/// Reads dates typed in by the user
+pub mod date {
+    use std::{
+        fmt::{self, Display},
+        str::FromStr,
+    };
+
+    use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+    use serde::{Deserialize, Serialize};
+
+    /// A day, optionally at a specific time of that day
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    #[serde(into = "String", try_from = "String")]
+    pub struct When {
+        /// The day
+        pub date: NaiveDate,
+        /// The time of day. `None` means at some point during the day
+        pub time: Option<NaiveTime>,
+    }
+
+    impl When {
+        /// Has this moment already passed?
+        #[must_use]
+        pub fn is_overdue(&self, now: NaiveDateTime) -> bool {
+            self.time.map_or_else(
+                || self.date < now.date(),
+                |time| self.date.and_time(time) < now,
+            )
+        }
+
+        /// Is this moment on the same day as `now`?
+        #[must_use]
+        pub fn is_today(&self, now: NaiveDateTime) -> bool {
+            self.date == now.date()
+        }
+    }
+
+    impl Display for When {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.date.format("%Y-%m-%d"))?;
+            if let Some(time) = self.time {
+                write!(f, " {}", time.format("%H:%M"))?;
+            }
+            Ok(())
+        }
+    }
+
+    impl From<When> for String {
+        fn from(value: When) -> Self {
+            value.to_string()
+        }
+    }
+
+    impl TryFrom<String> for When {
+        type Error = String;
+
+        fn try_from(value: String) -> Result<Self, Self::Error> {
+            // Stored dates are always absolute so `now` doesn't matter
+            parse(&value, NaiveDateTime::default())
+        }
+    }
+
+    /// How often an item comes back after it has been done
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(into = "String", try_from = "String")]
+    pub enum Repeat {
+        /// Every day
+        Daily,
+        /// Every week on these days, starting with monday
+        Weekly(Vec<Weekday>),
+        /// Every month on this day. Shorter months use their last day instead
+        Monthly(u32),
+        /// This many days after it was last done
+        After(u32),
+    }
+
+    impl Repeat {
+        /// The day the next occurrence is due if the current one was due on `due`
+        /// and got done on `done`. It's always after both of them
+        #[must_use]
+        pub fn next(&self, due: Option<NaiveDate>, done: NaiveDate) -> Option<NaiveDate> {
+            let after = due.map_or(done, |x| x.max(done));
+            match self {
+                Self::Daily => after.succ_opt(),
+                Self::Weekly(days) => (1..=7)
+                    .filter_map(|x| after.checked_add_days(Days::new(x)))
+                    .find(|x| days.contains(&x.weekday())),
+                Self::Monthly(day) => (0..=1)
+                    .filter_map(|x| {
+                        let month = after.with_day(1)?.checked_add_months(Months::new(x))?;
+                        let last = month.checked_add_months(Months::new(1))?.pred_opt()?;
+                        month.with_day((*day).min(last.day()))
+                    })
+                    .find(|x| *x > after),
+                Self::After(days) => done.checked_add_days(Days::new((*days).into())),
+            }
+        }
+    }
+
+    impl Display for Repeat {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::Daily => write!(f, "daily"),
+                Self::Weekly(days) => write!(
+                    f,
+                    "weekly {}",
+                    days.iter()
+                        .map(|x| x.to_string().to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                ),
+                Self::Monthly(day) => write!(f, "monthly {day}"),
+                Self::After(days) => write!(f, "every {days} days"),
+            }
+        }
+    }
+
+    /// Reads `daily`, `weekly mon thu`, `monthly 15` or `every 3 days`
+    impl FromStr for Repeat {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let input = s.trim().to_lowercase();
+            let words = input
+                .split(|x: char| x.is_whitespace() || x == ',')
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<_>>();
+            match words.as_slice() {
+                ["daily"] => Ok(Self::Daily),
+                ["weekly", days @ ..] if !days.is_empty() => {
+                    let mut days = days
+                        .iter()
+                        .map(|x| {
+                            x.parse::<Weekday>()
+                                .map_err(|_| format!("Unknown day: {x}"))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    days.sort_by_key(Weekday::num_days_from_monday);
+                    days.dedup();
+                    Ok(Self::Weekly(days))
+                }
+                ["monthly", day] => day
+                    .parse()
+                    .ok()
+                    .filter(|x| (1..=31).contains(x))
+                    .map(Self::Monthly)
+                    .ok_or_else(|| format!("Not a day of the month: {day}")),
+                ["every", days, "day" | "days"] => days
+                    .parse()
+                    .ok()
+                    .filter(|&x| x != 0)
+                    .map(Self::After)
+                    .ok_or_else(|| format!("Not a number of days: {days}")),
+                _ => Err(format!("Unknown repetition: {}", s.trim())),
+            }
+        }
+    }
+
+    impl From<Repeat> for String {
+        fn from(value: Repeat) -> Self {
+            value.to_string()
+        }
+    }
+
+    impl TryFrom<String> for Repeat {
+        type Error = String;
+
+        fn try_from(value: String) -> Result<Self, Self::Error> {
+            value.parse()
+        }
+    }
+
+    /// Reads a date relative to `now`.
+    ///
+    /// The day can be written as `2026-11-01`, `today`, `tomorrow`, `yesterday`, a weekday like `fri`
+    /// or an offset like `+3d`, `+2w`, `+1m` or `+1y`. It may be followed by a
+    /// time like `17:00` or `5pm`. A time on its own means today
+    ///
+    /// # Errors
+    /// Returns a message describing the part that couldn't be read
+    pub fn parse(input: &str, now: NaiveDateTime) -> Result<When, String> {
+        let input = input.trim().to_lowercase();
+        let words = input.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            [] => Err("No date given".to_string()),
+            [time] if parse_time(time).is_some() => Ok(When {
+                date: now.date(),
+                time: parse_time(time),
+            }),
+            [day, rest @ ..] => {
+                let date =
+                    parse_day(day, now.date()).ok_or_else(|| format!("Unknown date: {day}"))?;
+                let time = match rest {
+                    [] => None,
+                    [time] => {
+                        Some(parse_time(time).ok_or_else(|| format!("Unknown time: {time}"))?)
+                    }
+                    [_, rest @ ..] => return Err(format!("Unexpected: {}", rest.join(" "))),
+                };
+                Ok(When { date, time })
+            }
+        }
+    }
+
+    fn parse_day(day: &str, today: NaiveDate) -> Option<NaiveDate> {
+        match day {
+            "today" => return Some(today),
+            "tomorrow" => return today.checked_add_days(Days::new(1)),
+            "yesterday" => return today.checked_sub_days(Days::new(1)),
+            _ => (),
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+            return Some(date);
+        }
+        if let Some(offset) = day.strip_prefix('+') {
+            let split = offset
+                .find(|x: char| !x.is_ascii_digit())
+                .unwrap_or(offset.len());
+            let (amount, unit) = offset.split_at(split);
+            let amount = amount.parse::<u32>().ok()?;
+            return match unit {
+                "" | "d" => today.checked_add_days(Days::new(amount.into())),
+                "w" => today.checked_add_days(Days::new(u64::from(amount) * 7)),
+                "m" => today.checked_add_months(Months::new(amount)),
+                "y" => today.checked_add_months(Months::new(amount.checked_mul(12)?)),
+                _ => None,
+            };
+        }
+        // The next time it's that weekday, today included
+        let weekday = day.parse::<Weekday>().ok()?;
+        let ahead =
+            (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+        today.checked_add_days(Days::new(ahead.into()))
+    }
+
+    fn parse_time(time: &str) -> Option<NaiveTime> {
+        let (time, offset) = if let Some(time) = time.strip_suffix("am") {
+            (time, 0)
+        } else if let Some(time) = time.strip_suffix("pm") {
+            (time, 12)
+        } else {
+            return NaiveTime::parse_from_str(time, "%H:%M").ok();
+        };
+        let (hour, minute) = time.split_once(':').unwrap_or((time, "0"));
+        let hour = hour.parse::<u32>().ok().filter(|x| (1..=12).contains(x))?;
+        NaiveTime::from_hms_opt(hour % 12 + offset, minute.parse().ok()?, 0)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::testing::now;
+
+        fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+            NaiveDate::from_ymd_opt(y, m, d).unwrap()
+        }
+
+        fn time(h: u32, m: u32) -> Option<NaiveTime> {
+            NaiveTime::from_hms_opt(h, m, 0)
+        }
+
+        fn date(input: &str) -> NaiveDate {
+            parse(input, now()).unwrap().date
+        }
+
+        #[test]
+        fn absolute_dates() {
+            assert_eq!(
+                parse("2026-11-01", now()),
+                Ok(When {
+                    date: day(2026, 11, 1),
+                    time: None
+                })
+            );
+            assert_eq!(date(" Today "), day(2026, 10, 18));
+            assert_eq!(date("tomorrow"), day(2026, 10, 19));
+            assert_eq!(date("yesterday"), day(2026, 10, 17));
+        }
+
+        #[test]
+        fn relative_dates() {
+            assert_eq!(date("+3"), day(2026, 10, 21));
+            assert_eq!(date("+3d"), day(2026, 10, 21));
+            assert_eq!(date("+2w"), day(2026, 11, 1));
+            assert_eq!(date("+1m"), day(2026, 11, 18));
+            assert_eq!(date("+1y"), day(2027, 10, 18));
+            // Months that are too short end on their last day
+            let end_of_january = day(2026, 1, 31).and_hms_opt(0, 0, 0).unwrap();
+            assert_eq!(parse("+1m", end_of_january).unwrap().date, day(2026, 2, 28));
+        }
+
+        #[test]
+        fn weekdays_include_today() {
+            assert_eq!(date("sun"), day(2026, 10, 18));
+            assert_eq!(date("mon"), day(2026, 10, 19));
+            assert_eq!(date("friday"), day(2026, 10, 23));
+            assert_eq!(date("sat"), day(2026, 10, 24));
+        }
+
+        #[test]
+        fn times() {
+            assert_eq!(
+                parse("tomorrow 5pm", now()),
+                Ok(When {
+                    date: day(2026, 10, 19),
+                    time: time(17, 0)
+                })
+            );
+            assert_eq!(
+                parse("17:30", now()),
+                Ok(When {
+                    date: day(2026, 10, 18),
+                    time: time(17, 30)
+                })
+            );
+            assert_eq!(parse("today 12am", now()).unwrap().time, time(0, 0));
+            assert_eq!(parse("today 12pm", now()).unwrap().time, time(12, 0));
+            assert_eq!(parse("today 9:15am", now()).unwrap().time, time(9, 15));
+        }
+
+        #[test]
+        fn errors() {
+            assert!(parse("", now()).is_err());
+            assert!(parse("someday", now()).is_err());
+            assert!(parse("+3x", now()).is_err());
+            assert!(parse("today 25:00", now()).is_err());
+            assert!(parse("today 13pm", now()).is_err());
+            assert!(parse("today 5pm sharp", now()).is_err());
+        }
+
+        #[test]
+        fn reads_repetitions() {
+            assert_eq!("Daily".parse(), Ok(Repeat::Daily));
+            assert_eq!(
+                "weekly thu, mon mon".parse(),
+                Ok(Repeat::Weekly(vec![Weekday::Mon, Weekday::Thu]))
+            );
+            assert_eq!("monthly 31".parse(), Ok(Repeat::Monthly(31)));
+            assert_eq!("every 1 day".parse(), Ok(Repeat::After(1)));
+            assert_eq!("every 3 days".parse(), Ok(Repeat::After(3)));
+            for wrong in [
+                "",
+                "weekly",
+                "weekly funday",
+                "monthly 0",
+                "monthly 32",
+                "every 0 days",
+            ] {
+                assert!(wrong.parse::<Repeat>().is_err(), "{wrong}");
+            }
+        }
+
+        #[test]
+        fn repetitions_read_what_they_show() {
+            for repeat in [
+                Repeat::Daily,
+                Repeat::Weekly(vec![Weekday::Tue, Weekday::Sun]),
+                Repeat::Monthly(15),
+                Repeat::After(10),
+            ] {
+                assert_eq!(repeat.to_string().parse(), Ok(repeat));
+            }
+        }
+
+        #[test]
+        fn next_occurrences() {
+            let sunday = day(2026, 10, 18);
+            assert_eq!(Repeat::Daily.next(None, sunday), Some(day(2026, 10, 19)));
+            // Done late, so the next one comes after the day it was done
+            assert_eq!(
+                Repeat::Daily.next(Some(day(2026, 10, 1)), sunday),
+                Some(day(2026, 10, 19))
+            );
+            let weekly = Repeat::Weekly(vec![Weekday::Mon, Weekday::Sun]);
+            assert_eq!(weekly.next(None, sunday), Some(day(2026, 10, 19)));
+            assert_eq!(
+                weekly.next(None, day(2026, 10, 19)),
+                Some(day(2026, 10, 25))
+            );
+            // Counted from when it was done, not when it was due
+            assert_eq!(
+                Repeat::After(3).next(Some(day(2026, 10, 30)), sunday),
+                Some(day(2026, 10, 21))
+            );
+        }
+
+        #[test]
+        fn monthly_rolls_over_the_end_of_the_month() {
+            let monthly = Repeat::Monthly(31);
+            assert_eq!(
+                monthly.next(Some(day(2026, 1, 31)), day(2026, 1, 31)),
+                Some(day(2026, 2, 28))
+            );
+            assert_eq!(
+                monthly.next(Some(day(2026, 2, 28)), day(2026, 2, 28)),
+                Some(day(2026, 3, 31))
+            );
+            assert_eq!(
+                Repeat::Monthly(30).next(None, day(2028, 1, 30)),
+                Some(day(2028, 2, 29))
+            );
+            assert_eq!(
+                Repeat::Monthly(15).next(None, day(2026, 12, 20)),
+                Some(day(2027, 1, 15))
+            );
+            assert_eq!(
+                Repeat::Monthly(15).next(None, day(2026, 12, 10)),
+                Some(day(2026, 12, 15))
+            );
+        }
+    }
+}
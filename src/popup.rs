@@ -1,7 +1,15 @@
 //! Handles information about popups
+use chrono::Local;
 use crossterm::event::KeyCode;
 
-use crate::{app::{CurrentEdit, SubstateMode}, help, parse::todo::Items};
+use crate::{
+    app::{CurrentEdit, SubstateMode},
+    help,
+    parse::{
+        date,
+        todo::{self, Items},
+    },
+};
 
 /// State data for a popup
 #[derive(Debug)]
@@ -12,10 +20,22 @@ pub enum Popup {
         title: String,
         /// The description of the item
         description: String,
+        /// The tags of the item, separated by spaces
+        tags: String,
+        /// When the item is due, as typed in
+        due: String,
+        /// When the item is scheduled, as typed in
+        scheduled: String,
+        /// How often the item comes back, as typed in
+        repeat: String,
+        /// Why the item couldn't be saved
+        error: Option<String>,
         /// The currently highlighted/edited part of the popup
         editing: CurrentEdit,
-        /// The index of the currently edited item if its empty then a new item is being added
-        to_change: Option<usize>,
+        /// The id of the currently edited item if its empty then a new item is being added
+        to_change: Option<todo::Id>,
+        /// The item a new item is added as a subtask of
+        parent: Option<todo::Id>,
     },
 
     /// Show help menu
@@ -23,6 +43,78 @@ pub enum Popup {
         /// the index of the currently selected item
         usize,
     ),
+
+    /// Quitting with unsaved changes
+    ConfirmQuit,
+
+    /// Browsing the archive of the current list
+    Archive(
+        /// the index of the currently selected item
+        usize,
+    ),
+
+    /// Browsing the deleted items of the current list
+    Trash(
+        /// the index of the currently selected item
+        usize,
+    ),
+
+    /// Deleting an item that has subtasks or a long description
+    ConfirmDelete,
+
+    /// Looking at every change made to the selected item
+    History(
+        /// How far the history has been scrolled
+        usize,
+    ),
+
+    /// Typing in the name of a list
+    ListName {
+        /// The name typed so far
+        name: String,
+        /// What to do with the name once it's entered
+        action: ListAction,
+    },
+
+    /// Picking the list to move or copy the selected item into
+    PickList {
+        /// The index of the highlighted list in [`crate::static_info::StaticInfo::item_list_names`]
+        selected: usize,
+        /// Is the item copied instead of moved?
+        copy: bool,
+    },
+
+    /// Deleting a whole list
+    ConfirmDeleteList(
+        /// The name of the list
+        String,
+    ),
+}
+
+/// What should be done with a list name entered in [`Popup::ListName`]
+#[derive(Debug, Clone)]
+pub enum ListAction {
+    /// Create a new empty list
+    Create,
+    /// Rename the list with this name
+    Rename(String),
+    /// Make a copy of the list with this name
+    Duplicate(String),
+    /// Save this search as a smart list
+    SaveSearch(String),
+}
+
+impl ListAction {
+    /// A short description used as the title of the popup
+    #[must_use]
+    pub const fn title(&self) -> &'static str {
+        match self {
+            Self::Create => "New list",
+            Self::Rename(_) => "Rename list",
+            Self::Duplicate(_) => "Duplicate list",
+            Self::SaveSearch(_) => "Save search",
+        }
+    }
 }
 
 /// Describes what action should be taken when a popup is present
@@ -35,12 +127,38 @@ pub enum ReturnAction {
     /// Edit the item
     /// 0: the item to be edited
     /// 1: its new value
-    Edit(usize, (Box<str>, (Box<str>, usize))),
+    Edit(todo::Id, todo::Item),
     /// Add an item
     /// The value to push
-    Add((Box<str>, (Box<str>, usize))),
+    Add(todo::Item),
     /// Enter a substate
     EnterSubState(SubstateMode),
+    /// Quit the app
+    Quit {
+        /// Save everything before quitting
+        save: bool,
+    },
+    /// Create, rename or duplicate a list or save a search
+    /// 0: what to do
+    /// 1: the (new) name of the list
+    List(ListAction, String),
+    /// Delete the list with this name
+    DeleteList(String),
+    /// Move the archived item at this index back into the list
+    Restore(usize),
+    /// Delete the selected item
+    Delete,
+    /// Move the deleted item at this index back into the list
+    RestoreDeleted(usize),
+    /// Throw the deleted item at this index away for good
+    Purge(usize),
+    /// Move or copy the selected item into another list, see [`Popup::PickList`]
+    Transfer {
+        /// The index of the list
+        list: usize,
+        /// Keep the item where it is too
+        copy: bool,
+    },
 }
 
 impl Popup {
@@ -49,66 +167,196 @@ impl Popup {
     /// Handles input
     pub fn handle_input(&mut self, key: KeyCode, help: &Items<help::Item>) -> ReturnAction {
         match self {
-            Self::Edit {
-                ref mut title,
-                ref mut description,
-                ref mut editing,
-                to_change,
-            } => match key {
-                KeyCode::Backspace => drop(
-                    match editing {
-                        CurrentEdit::Title => title,
-                        CurrentEdit::Body => description,
-                    }
-                    .pop(),
-                ),
-                KeyCode::Esc => return ReturnAction::Exit,
-                KeyCode::Enter => {
-                    return to_change.as_mut().map_or_else(
-                        || {
-                            ReturnAction::Add((
-                                title.to_owned().into_boxed_str(),
-                                (description.to_owned().into_boxed_str(), 0),
-                            ))
-                        },
-                        |x| {
-                            ReturnAction::Edit(
-                                *x,
-                                (
-                                    title.to_owned().into_boxed_str(),
-                                    (description.to_owned().into_boxed_str(), 0),
-                                ),
-                            )
-                        },
-                    )
-                }
-                KeyCode::Tab => {
-                    *editing = match editing {
-                        CurrentEdit::Title => CurrentEdit::Body,
-                        CurrentEdit::Body => CurrentEdit::Title,
-                    }
-                }
-                KeyCode::Char(x) => match editing {
-                    CurrentEdit::Title => title,
-                    CurrentEdit::Body => description,
-                }
-                .push(x),
-                _ => (),
-            },
+            Self::Edit { .. } => return self.handle_edit(key),
             Self::Help(ref mut x) => match key {
                 KeyCode::Char('q') => return ReturnAction::Exit,
-                KeyCode::Char('j') => {
-                    if *x != help.items.len() - 1 {
-                        *x += 1;
-                    }
-                }
+                KeyCode::Char('j') if *x != help.items.len() - 1 => *x += 1,
                 KeyCode::Char('k') => *x = x.saturating_sub(1),
                 KeyCode::Char('/') => {
                     return ReturnAction::EnterSubState(SubstateMode::Filter(String::new()))
                 }
                 _ => (),
             },
+            Self::ListName { name, action } => match key {
+                KeyCode::Esc => return ReturnAction::Exit,
+                KeyCode::Enter => return ReturnAction::List(action.clone(), name.clone()),
+                KeyCode::Backspace => drop(name.pop()),
+                KeyCode::Char(x) => name.push(x),
+                _ => (),
+            },
+            Self::ConfirmDeleteList(name) => match key {
+                KeyCode::Char('y') => return ReturnAction::DeleteList(name.clone()),
+                KeyCode::Char('n' | 'q') | KeyCode::Esc => return ReturnAction::Exit,
+                _ => (),
+            },
+            // The app keeps the selection within the archive
+            Self::Archive(ref mut x) => match key {
+                KeyCode::Char('q') | KeyCode::Esc => return ReturnAction::Exit,
+                KeyCode::Char('j') | KeyCode::Down => *x += 1,
+                KeyCode::Char('k') | KeyCode::Up => *x = x.saturating_sub(1),
+                KeyCode::Char('r') | KeyCode::Enter => return ReturnAction::Restore(*x),
+                _ => (),
+            },
+            // The app keeps the selection within the lists
+            Self::PickList {
+                ref mut selected,
+                copy,
+            } => match key {
+                KeyCode::Char('q') | KeyCode::Esc => return ReturnAction::Exit,
+                KeyCode::Char('j') | KeyCode::Down => *selected += 1,
+                KeyCode::Char('k') | KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Enter => {
+                    return ReturnAction::Transfer {
+                        list: *selected,
+                        copy: *copy,
+                    }
+                }
+                _ => (),
+            },
+            // The app keeps the selection within the trash
+            Self::Trash(ref mut x) => match key {
+                KeyCode::Char('q') | KeyCode::Esc => return ReturnAction::Exit,
+                KeyCode::Char('j') | KeyCode::Down => *x += 1,
+                KeyCode::Char('k') | KeyCode::Up => *x = x.saturating_sub(1),
+                KeyCode::Char('r') | KeyCode::Enter => return ReturnAction::RestoreDeleted(*x),
+                KeyCode::Char('d') => return ReturnAction::Purge(*x),
+                _ => (),
+            },
+            Self::ConfirmDelete => match key {
+                KeyCode::Char('y') => return ReturnAction::Delete,
+                KeyCode::Char('n' | 'q') | KeyCode::Esc => return ReturnAction::Exit,
+                _ => (),
+            },
+            // The app keeps the scroll within the history
+            Self::History(ref mut x) => match key {
+                KeyCode::Char('q') | KeyCode::Esc => return ReturnAction::Exit,
+                KeyCode::Char('j') | KeyCode::Down => *x += 1,
+                KeyCode::Char('k') | KeyCode::Up => *x = x.saturating_sub(1),
+                _ => (),
+            },
+            Self::ConfirmQuit => match key {
+                KeyCode::Char('y') => return ReturnAction::Quit { save: false },
+                KeyCode::Char('s') => return ReturnAction::Quit { save: true },
+                KeyCode::Char('n' | 'q') | KeyCode::Esc => return ReturnAction::Exit,
+                _ => (),
+            },
+        }
+        ReturnAction::Nothing
+    }
+
+    /// Handles input while editing an item
+    fn handle_edit(&mut self, key: KeyCode) -> ReturnAction {
+        let Self::Edit {
+            ref mut title,
+            ref mut description,
+            ref mut tags,
+            ref mut due,
+            ref mut scheduled,
+            ref mut repeat,
+            ref mut editing,
+            ..
+        } = self
+        else {
+            return ReturnAction::Nothing;
+        };
+        match key {
+            KeyCode::Backspace => drop(
+                match editing {
+                    CurrentEdit::Title => title,
+                    CurrentEdit::Body => description,
+                    CurrentEdit::Tags => tags,
+                    CurrentEdit::Due => due,
+                    CurrentEdit::Scheduled => scheduled,
+                    CurrentEdit::Repeat => repeat,
+                }
+                .pop(),
+            ),
+            KeyCode::Esc => return ReturnAction::Exit,
+            KeyCode::Enter => return self.finish_edit(),
+            KeyCode::Tab => {
+                *editing = match editing {
+                    CurrentEdit::Title => CurrentEdit::Body,
+                    CurrentEdit::Body => CurrentEdit::Tags,
+                    CurrentEdit::Tags => CurrentEdit::Due,
+                    CurrentEdit::Due => CurrentEdit::Scheduled,
+                    CurrentEdit::Scheduled => CurrentEdit::Repeat,
+                    CurrentEdit::Repeat => CurrentEdit::Title,
+                }
+            }
+            KeyCode::Char(x) => match editing {
+                CurrentEdit::Title => title,
+                CurrentEdit::Body => description,
+                CurrentEdit::Tags => tags,
+                CurrentEdit::Due => due,
+                CurrentEdit::Scheduled => scheduled,
+                CurrentEdit::Repeat => repeat,
+            }
+            .push(x),
+            _ => (),
         }
         ReturnAction::Nothing
     }
+
+    /// Turns the typed in fields into an item. If some field can't be read the
+    /// popup stays open and shows why
+    fn finish_edit(&mut self) -> ReturnAction {
+        let Self::Edit {
+            title,
+            description,
+            tags,
+            due,
+            scheduled,
+            repeat,
+            error,
+            to_change,
+            parent,
+            ..
+        } = self
+        else {
+            return ReturnAction::Nothing;
+        };
+        let now = Local::now().naive_local();
+        let parse_date = |x: &str, name| {
+            if x.trim().is_empty() {
+                Ok(None)
+            } else {
+                date::parse(x, now)
+                    .map(Some)
+                    .map_err(|e| format!("{name}: {e}"))
+            }
+        };
+        let dates =
+            parse_date(due, "Due").and_then(|due| Ok((due, parse_date(scheduled, "Scheduled")?)));
+        let (due, scheduled) = match dates {
+            Ok(x) => x,
+            Err(e) => {
+                *error = Some(e);
+                return ReturnAction::Nothing;
+            }
+        };
+        let repeat = if repeat.trim().is_empty() {
+            None
+        } else {
+            match repeat.parse() {
+                Ok(x) => Some(x),
+                Err(e) => {
+                    *error = Some(format!("Repeat: {e}"));
+                    return ReturnAction::Nothing;
+                }
+            }
+        };
+        let item = todo::Item {
+            title: title.to_owned().into_boxed_str(),
+            description: description.to_owned().into_boxed_str(),
+            tags: todo::parse_tags(tags),
+            due,
+            scheduled,
+            repeat,
+            parent: *parent,
+            ..Default::default()
+        };
+        to_change.map_or(ReturnAction::Add(item.clone()), |x| {
+            ReturnAction::Edit(x, item)
+        })
+    }
 }
@@ -1,13 +1,29 @@
 //! Infarmation related to staring things that won't get modified regularly
-use std::{collections::HashMap, error::Error, fs::File, io::BufReader, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    error::Error,
+    ffi::OsString,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
 
-use serde::Deserialize;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    filter::{self, Context},
     help,
-    parse::todo::{self, Items},
+    ordered_list::OrderedList,
+    parse::{
+        date::{Repeat, When},
+        todo::{self, Change, Items, Priority, SortMode},
+    },
 };
 
+/// How many old versions of the lists file are kept around by default
+pub const DEFAULT_BACKUPS: usize = 3;
+
 /// The main struct of this module
 #[derive(Debug, Default)]
 pub struct StaticInfo {
@@ -15,6 +31,15 @@ pub struct StaticInfo {
     pub help: Items<help::Item>,
     /// All selectable options
     pub lists: HashMap<String, Items<todo::Item>>,
+    /// Saved searches by name. They are shown as smart lists that collect the
+    /// matching items of every list
+    pub searches: BTreeMap<String, String>,
+    /// The file the lists were read from and get saved to
+    pub lists_path: PathBuf,
+    /// How many backups (`lists.json.1`, `lists.json.2`, ...) to keep when saving
+    pub backups: usize,
+    /// The backup the lists were read from because the lists file couldn't be read
+    pub restored_from: Option<PathBuf>,
 }
 
 impl StaticInfo {
@@ -26,12 +51,89 @@ impl StaticInfo {
     where
         P: AsRef<Path>,
     {
+        let (lists_parsed, searches) = parse(&lists)?;
         Ok(Self {
             help: help::parse(help)?,
-            lists: parse(lists)?,
+            lists_path: lists.as_ref().to_path_buf(),
+            lists: lists_parsed,
+            searches,
+            backups: DEFAULT_BACKUPS,
+            restored_from: None,
+        })
+    }
+
+    /// Like [`Self::from`], but if the lists file is missing or corrupt the newest
+    /// of the `backups` backups that parses is used instead, see
+    /// [`Self::restored_from`]. Saving still writes to `lists`
+    ///
+    /// # Errors
+    /// 1. The help file couldn't be read
+    /// 2. Neither the lists file nor any of its backups could be read
+    pub fn from_or_backup<P>(lists: P, help: P, backups: usize) -> Result<Self, Box<dyn Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let help = help::parse(help)?;
+        let lists_path = lists.as_ref().to_path_buf();
+        let ((parsed, searches), restored_from) = match parse(&lists_path) {
+            Ok(x) => (x, None),
+            Err(err) => (1..=backups)
+                .map(|n| backup_path(&lists_path, n))
+                .find_map(|x| Some((parse(&x).ok()?, Some(x))))
+                .ok_or(err)?,
+        };
+        Ok(Self {
+            help,
+            lists: parsed,
+            searches,
+            lists_path,
+            backups,
+            restored_from,
         })
     }
 
+    /// Writes all lists back to the file they were read from.
+    /// `current` is the list that has been taken out using [`Self::get`]
+    ///
+    /// # Errors
+    /// 1. An io error due to failing to create or write the file
+    /// 2. Failing to serialize the lists
+    pub fn save(&self, current: Option<(&str, &Items<todo::Item>)>) -> Result<(), Box<dyn Error>> {
+        // Everything is written to a temporary file first so that a crash
+        // midway never leaves a half written lists file behind
+        let tmp = sibling_path(&self.lists_path, ".tmp");
+        let file = File::create(&tmp)?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(
+            &mut writer,
+            &serialize(&self.lists, &self.searches, current),
+        )?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+
+        self.rotate_backups()?;
+        fs::rename(tmp, &self.lists_path)?;
+        Ok(())
+    }
+
+    /// Shifts every backup one step back (`.1` becomes `.2` etc.), drops the
+    /// oldest one and copies the current lists file into `.1`. A lists file
+    /// that can't be read isn't worth keeping and would push out a good backup
+    fn rotate_backups(&self) -> Result<(), Box<dyn Error>> {
+        if self.backups == 0 || parse(&self.lists_path).is_err() {
+            return Ok(());
+        }
+        for n in (1..self.backups).rev() {
+            let from = backup_path(&self.lists_path, n);
+            if from.exists() {
+                fs::rename(from, backup_path(&self.lists_path, n + 1))?;
+            }
+        }
+        fs::copy(&self.lists_path, backup_path(&self.lists_path, 1))?;
+        Ok(())
+    }
+
     /// gets a list from static info (removes it too)
     pub fn get<T>(&mut self, query: T) -> Option<Items<todo::Item>>
     where
@@ -39,40 +141,742 @@ impl StaticInfo {
     {
         self.lists.remove(&query.into())
     }
+
+    /// Puts a list back after it was taken out using [`Self::get`]
+    pub fn insert(&mut self, name: String, list: Items<todo::Item>) {
+        self.lists.insert(name, list);
+    }
+
+    /// Is there a list or a saved search called `name`?
+    #[must_use]
+    pub fn is_taken(&self, name: &str) -> bool {
+        self.lists.contains_key(name) || self.searches.contains_key(name)
+    }
+
+    /// Creates a new empty list.
+    /// Returns false if a list with that name already exists
+    pub fn create_list(&mut self, name: String) -> bool {
+        if self.is_taken(&name) {
+            return false;
+        }
+        self.lists.insert(name, Items::default());
+        true
+    }
+
+    /// Saves a search as a smart list.
+    /// Returns false if a list with that name already exists
+    pub fn save_search(&mut self, name: String, search: String) -> bool {
+        if self.is_taken(&name) {
+            return false;
+        }
+        self.searches.insert(name, search);
+        true
+    }
+
+    /// Moves a list or a saved search to a new name.
+    /// Returns false if `from` doesn't exist or `to` is already taken
+    pub fn rename_list(&mut self, from: &str, to: String) -> bool {
+        if self.is_taken(&to) {
+            return false;
+        }
+        if let Some(search) = self.searches.remove(from) {
+            self.searches.insert(to, search);
+            return true;
+        }
+        let Some(list) = self.lists.remove(from) else {
+            return false;
+        };
+        self.lists.insert(to, list);
+        true
+    }
+
+    /// Removes a list or a saved search.
+    /// Returns false if there is nothing called `name`
+    pub fn remove_list(&mut self, name: &str) -> bool {
+        self.searches.remove(name).is_some() || self.lists.remove(name).is_some()
+    }
+
+    /// Copies a list or a saved search to a new name.
+    /// Returns false if `from` doesn't exist or `to` is already taken
+    pub fn duplicate_list(&mut self, from: &str, to: String) -> bool {
+        if self.is_taken(&to) {
+            return false;
+        }
+        if let Some(search) = self.searches.get(from).cloned() {
+            self.searches.insert(to, search);
+            return true;
+        }
+        let Some(mut list) = self.lists.get(from).cloned() else {
+            return false;
+        };
+        // The copies are different items
+        todo::renew_ids(list.items.iter_mut().chain(&mut list.archive));
+        list.trash.clear();
+        self.lists.insert(to, list);
+        true
+    }
+
+    /// Every item of every list by its id.
+    /// `current` is the list that has been taken out using [`Self::get`]
+    #[must_use]
+    pub fn lookup<'a>(
+        &'a self,
+        current: Option<(&'a str, &'a Items<todo::Item>)>,
+    ) -> todo::Lookup<'a> {
+        self.lists
+            .iter()
+            .map(|(x, y)| (x.as_str(), y))
+            .chain(current)
+            .flat_map(|(name, list)| list.items.iter().map(move |x| (x.id, (name, x))))
+            .collect()
+    }
+
+    /// The names of all lists in alphabetical order, followed by the names of
+    /// the saved searches
+    #[must_use]
+    pub fn list_names(&self) -> Vec<&String> {
+        let mut names = self.item_list_names();
+        names.extend(self.searches.keys());
+        names
+    }
+
+    /// The names of the lists that actually hold items in alphabetical order
+    #[must_use]
+    pub fn item_list_names(&self) -> Vec<&String> {
+        let mut names = self.lists.keys().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Copies every item matching `search` out of all lists, together with the
+    /// name of the list each one came from. An empty search collects everything.
+    /// Changes to the copies are put back using [`Self::write_back`]
+    ///
+    /// # Errors
+    /// The search can't be read
+    pub fn smart_list(
+        &self,
+        search: &str,
+    ) -> Result<(Items<todo::Item>, HashMap<todo::Id, String>), String> {
+        let now = Local::now().naive_local();
+        let filter = filter::parse(search, now)?;
+        let items = self.lookup(None);
+        let mut sources = HashMap::new();
+        let mut found = Vec::new();
+        for name in self.item_list_names() {
+            let list = &self.lists[name];
+            let context = Context {
+                list: name,
+                items: &items,
+                now,
+            };
+            for item in list.items.iter() {
+                if filter.matches(item, &context) {
+                    sources.insert(item.id, name.clone());
+                    found.push(item.clone());
+                }
+            }
+        }
+        let sort = SortMode::default();
+        let items = Items {
+            items: OrderedList::with_order(sort.order(), found),
+            archive: Vec::new(),
+            trash: Vec::new(),
+            sort,
+        };
+        Ok((items, sources))
+    }
+
+    /// Puts the items of a smart list back into the lists they came from, see
+    /// [`Self::smart_list`]. Items that were archived or deleted in the smart list
+    /// are archived or deleted in their list too, items that got a different
+    /// list are moved
+    pub fn write_back(
+        &mut self,
+        smart_list: &Items<todo::Item>,
+        sources: &HashMap<todo::Id, String>,
+    ) {
+        let locations = self
+            .lookup(None)
+            .into_iter()
+            .map(|(id, (list, _))| (id, list.to_string()))
+            .collect::<HashMap<_, _>>();
+        for (&id, name) in sources {
+            if !self.lists.contains_key(name) {
+                continue;
+            }
+            let old = locations.get(&id).filter(|x| *x != name);
+            let mut moved = Vec::new();
+            if let Some(old) = old.and_then(|x| self.lists.get_mut(x)) {
+                if let Some(x) = old.position(id) {
+                    // Subtasks that aren't in the smart list follow the item,
+                    // the others are moved along with their own entry
+                    moved = old.remove_tree_until(x, |x| sources.contains_key(&x.id));
+                    moved.retain(|x| x.id != id);
+                }
+            }
+            let Some(list) = self.lists.get_mut(name) else {
+                continue;
+            };
+            for item in moved {
+                list.add(item);
+            }
+            list.archive.retain(|x| x.id != id);
+            list.trash.retain(|x| x.id != id);
+            let position = list.position(id);
+            if let Some(item) = smart_list.items.iter().find(|x| x.id == id) {
+                match position {
+                    // Unchanged items stay where they are
+                    Some(x) if list[x] == *item => (),
+                    // Only the list itself decides where its items go when
+                    // it's sorted manually
+                    Some(x) => drop(list.modify(x, |old| {
+                        let rank = old.rank;
+                        old.clone_from(item);
+                        old.rank = rank;
+                    })),
+                    None => drop(list.add(item.clone())),
+                }
+                continue;
+            }
+            let archived = smart_list.archive.iter().find(|x| x.id == id);
+            if let Some(x) = position {
+                // Subtasks that aren't in the smart list follow the item
+                let removed = list.remove_tree(x);
+                let bin = if archived.is_some() {
+                    &mut list.archive
+                } else {
+                    &mut list.trash
+                };
+                bin.extend(removed.into_iter().filter(|x| !sources.contains_key(&x.id)));
+            }
+            if let Some(item) = archived {
+                list.archive.push(item.clone());
+            }
+            if let Some(item) = smart_list.trash.iter().find(|x| x.id == id) {
+                list.trash.push(item.clone());
+            }
+        }
+    }
 }
 
-fn parse<P>(path: P) -> Result<HashMap<String, Items<todo::Item>>, Box<dyn Error>>
+/// The lists and the saved searches
+type Parsed = (HashMap<String, Items<todo::Item>>, BTreeMap<String, String>);
+
+fn parse<P>(path: P) -> Result<Parsed, Box<dyn Error>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
-    let parsed: HashMap<String, Vec<Item>> = serde_json::from_reader(reader)?;
-    Ok(parsed
+    let parsed: HashMap<String, Entry> = serde_json::from_reader(reader)?;
+    let mut lists = HashMap::new();
+    let mut searches = BTreeMap::new();
+    for (name, entry) in parsed {
+        match entry {
+            Entry::Smart { search } => drop(searches.insert(name, search)),
+            Entry::List(list) => drop(lists.insert(name, list.into())),
+        }
+    }
+    Ok((lists, searches))
+}
+
+/// The path of the `n`th backup of `path`
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    sibling_path(path, &format!(".{n}"))
+}
+
+/// `path` with `suffix` appended to its file name
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn serialize<'a>(
+    lists: &'a HashMap<String, Items<todo::Item>>,
+    searches: &'a BTreeMap<String, String>,
+    current: Option<(&'a str, &'a Items<todo::Item>)>,
+) -> BTreeMap<&'a str, Entry> {
+    lists
+        .iter()
+        .map(|(x, y)| (x.as_str(), y))
+        .chain(current)
+        .map(|(x, y)| (x, Entry::List(y.into())))
+        .chain(
+            searches
+                .iter()
+                .map(|(x, y)| (x.as_str(), Entry::Smart { search: y.clone() })),
+        )
+        .collect()
+}
+
+/// Anything stored under a name in the lists file
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum Entry {
+    /// A saved search
+    Smart {
+        search: String,
+    },
+    List(List),
+}
+
+/// How a single list is stored. Older files only contain the items
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum List {
+    Full {
+        items: Vec<Item>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        archive: Vec<Item>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        trash: Vec<Item>,
+        #[serde(default)]
+        sort: SortMode,
+    },
+    Plain(Vec<Item>),
+}
+
+impl From<List> for Items<todo::Item> {
+    fn from(value: List) -> Self {
+        let (items, archive, trash, sort) = match value {
+            List::Full {
+                items,
+                archive,
+                trash,
+                sort,
+            } => (items, archive, trash, sort),
+            List::Plain(items) => (items, Vec::new(), Vec::new(), SortMode::default()),
+        };
+        // The items are stored in their manual order
+        let mut items = flatten(items);
+        for (i, item) in items.iter_mut().enumerate() {
+            item.rank = i;
+        }
+        Self {
+            items: OrderedList::with_order(sort.order(), items),
+            archive: flatten(archive),
+            trash: flatten(trash),
+            sort,
+        }
+    }
+}
+
+impl From<&Items<todo::Item>> for List {
+    fn from(value: &Items<todo::Item>) -> Self {
+        let mut items = value.items.iter().collect::<Vec<_>>();
+        items.sort_by_key(|x| x.rank);
+        Self::Full {
+            items: nest(items),
+            archive: nest(&value.archive),
+            trash: nest(&value.trash),
+            sort: value.sort,
+        }
+    }
+}
+
+/// Turns nested items into a flat list in which subtasks point at their parent
+fn flatten(items: Vec<Item>) -> Vec<todo::Item> {
+    let mut flat = Vec::new();
+    let mut stack = items
         .into_iter()
-        .map(|(x, y)| {
-            (
-                x,
-                y.into_iter()
-                    .map(std::convert::Into::into)
-                    .collect::<Items<todo::Item>>(),
-            )
-        })
-        .collect::<HashMap<String, Items<todo::Item>>>())
+        .rev()
+        .map(|x| (None, x))
+        .collect::<Vec<_>>();
+    while let Some((parent, mut item)) = stack.pop() {
+        let children = std::mem::take(&mut item.children);
+        let mut item = todo::Item::from(item);
+        item.parent = parent.or(item.parent);
+        stack.extend(children.into_iter().rev().map(|x| (Some(item.id), x)));
+        flat.push(item);
+    }
+    flat
 }
 
-#[derive(Debug, Deserialize)]
+/// The opposite of [`flatten`]. Items whose parent is somewhere else, like a
+/// subtask in the archive, end up at the top and point at it instead
+fn nest<'a, I>(items: I) -> Vec<Item>
+where
+    I: IntoIterator<Item = &'a todo::Item> + Clone,
+{
+    let ids = items
+        .clone()
+        .into_iter()
+        .map(|x| x.id)
+        .collect::<HashSet<_>>();
+    let mut children = HashMap::<_, Vec<_>>::new();
+    for item in items {
+        let parent = item.parent.filter(|x| ids.contains(x));
+        children.entry(parent).or_default().push(item);
+    }
+    nest_children(None, &children)
+}
+
+fn nest_children(
+    parent: Option<todo::Id>,
+    children: &HashMap<Option<todo::Id>, Vec<&todo::Item>>,
+) -> Vec<Item> {
+    children.get(&parent).map_or_else(Vec::new, |items| {
+        items
+            .iter()
+            .map(|x| Item {
+                // Subtasks inside their parent don't need to point at it
+                parent: x.parent.filter(|_| parent.is_none()),
+                children: nest_children(Some(x.id), children),
+                ..Item::from(*x)
+            })
+            .collect()
+    })
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 struct Item {
+    // Older files don't have ids yet, they are generated when reading them
+    #[serde(default)]
+    id: todo::Id,
     title: String,
     description: String,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completed: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due: Option<When>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scheduled: Option<When>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    history: Vec<Change>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    collapsed: bool,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    blocked_by: BTreeSet<todo::Id>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    repeat: Option<Repeat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next: Option<todo::Id>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<todo::Id>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    children: Vec<Self>,
 }
 
 impl From<Item> for todo::Item {
     fn from(value: Item) -> Self {
         Self {
+            id: value.id,
             title: value.title.into_boxed_str(),
             description: value.description.into_boxed_str(),
             description_scroll: 0,
+            tags: value.tags,
+            completed: value.completed,
+            due: value.due,
+            scheduled: value.scheduled,
+            priority: value.priority,
+            created: value.created,
+            modified: value.modified,
+            history: value.history,
+            parent: value.parent,
+            collapsed: value.collapsed,
+            blocked_by: value.blocked_by,
+            repeat: value.repeat,
+            next: value.next,
+            rank: 0,
+        }
+    }
+}
+
+impl From<&todo::Item> for Item {
+    fn from(value: &todo::Item) -> Self {
+        Self {
+            id: value.id,
+            title: value.title.to_string(),
+            description: value.description.to_string(),
+            tags: value.tags.clone(),
+            completed: value.completed,
+            due: value.due,
+            scheduled: value.scheduled,
+            priority: value.priority,
+            created: value.created,
+            modified: value.modified,
+            history: value.history.clone(),
+            collapsed: value.collapsed,
+            blocked_by: value.blocked_by.clone(),
+            repeat: value.repeat.clone(),
+            next: value.next,
+            parent: value.parent,
+            children: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(title: &str, parent: Option<&todo::Item>) -> todo::Item {
+        todo::Item {
+            title: title.into(),
+            parent: parent.map(|x| x.id),
+            ..todo::Item::default()
+        }
+    }
+
+    fn tagged(title: &str, parent: Option<&todo::Item>) -> todo::Item {
+        todo::Item {
+            tags: BTreeSet::from(["home".to_string()]),
+            ..item(title, parent)
+        }
+    }
+
+    /// A list called `name` holding `items`, next to an empty "Work" list
+    fn with_list(name: &str, items: Vec<todo::Item>) -> StaticInfo {
+        let mut list = Items::default();
+        for x in items {
+            list.add(x);
         }
+        StaticInfo {
+            lists: HashMap::from([(name.to_string(), list), ("Work".into(), Items::default())]),
+            ..StaticInfo::default()
+        }
+    }
+
+    /// The titles of some items
+    fn titles<'a>(items: impl IntoIterator<Item = &'a todo::Item>) -> Vec<&'a str> {
+        items.into_iter().map(|x| &*x.title).collect()
+    }
+
+    /// An empty directory only used by the test called `name`
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("todo-{name}-{}", std::process::id()));
+        drop(fs::remove_dir_all(&dir));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn open(lists: &Path) -> StaticInfo {
+        StaticInfo::from_or_backup(lists, Path::new("help.json"), DEFAULT_BACKUPS).unwrap()
+    }
+
+    /// The names of the lists in a lists file
+    fn names(path: &Path) -> Vec<String> {
+        let mut names = parse(path).unwrap().0.into_keys().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Stores the list the way it is saved and reads it back
+    fn round_trip(items: &Items<todo::Item>) -> Items<todo::Item> {
+        let json = serde_json::to_string(&List::from(items)).unwrap();
+        serde_json::from_str::<List>(&json).unwrap().into()
+    }
+
+    #[test]
+    fn subtasks_keep_their_parent_across_sections() {
+        let parent = item("Move house", None);
+        let packed = item("Pack the books", Some(&parent));
+        let boxes = item("Buy boxes", Some(&packed));
+        let archived = item("Find a van", Some(&parent));
+        let trashed = item("Call the bank", Some(&boxes));
+        let mut items = Items {
+            archive: vec![archived],
+            trash: vec![trashed],
+            ..Items::default()
+        };
+        items.set_sort(SortMode::Manual);
+        for x in [parent.clone(), packed, boxes] {
+            items.add(x);
+        }
+        let read = round_trip(&items);
+        assert_eq!(read.items.to_vec(), items.items.to_vec());
+        assert_eq!(read.archive, items.archive);
+        assert_eq!(read.trash, items.trash);
+        // Only the subtasks that are apart from their parent point at it
+        let List::Full { items, archive, .. } = List::from(&read) else {
+            unreachable!()
+        };
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].parent, None);
+        assert_eq!(items[0].children[0].parent, None);
+        assert_eq!(archive[0].parent, Some(parent.id));
+    }
+
+    #[test]
+    fn manual_order_survives_sorting_and_saving() {
+        let mut items = Items::default();
+        items.set_sort(SortMode::Manual);
+        for x in ["c", "a", "b"] {
+            items.add(item(x, None));
+        }
+        let titles = |items: &Items<todo::Item>| items.titles().join("");
+        items.set_sort(SortMode::Alphabetical);
+        assert_eq!(titles(&items), "abc");
+        let mut read = round_trip(&items);
+        assert_eq!(titles(&read), "abc");
+        let mut sort = read.sort;
+        while sort != SortMode::Manual {
+            sort = sort.next();
+            read.set_sort(sort);
+        }
+        assert_eq!(titles(&read), "cab");
+        // Moving an item while sorted otherwise starts from what is shown
+        items.swap(1, 2);
+        assert_eq!(titles(&items), "acb");
+        items.set_sort(SortMode::Alphabetical);
+        items.set_sort(SortMode::Manual);
+        assert_eq!(titles(&items), "acb");
+    }
+
+    #[test]
+    fn saving_rotates_the_backups() {
+        let dir = temp_dir("rotate");
+        let lists = dir.join("lists.json");
+        fs::write(&lists, r#"{"a": []}"#).unwrap();
+        let mut info = open(&lists);
+        for name in ["b", "c", "d", "e"] {
+            info.create_list(name.into());
+            info.save(None).unwrap();
+        }
+        assert_eq!(names(&lists), ["a", "b", "c", "d", "e"]);
+        assert_eq!(names(&backup_path(&lists, 1)), ["a", "b", "c", "d"]);
+        assert_eq!(names(&backup_path(&lists, 2)), ["a", "b", "c"]);
+        assert_eq!(names(&backup_path(&lists, 3)), ["a", "b"]);
+        // The oldest one, with only "a", is gone
+        assert!(!backup_path(&lists, 4).exists());
+    }
+
+    #[test]
+    fn a_corrupt_file_is_read_from_the_newest_good_backup() {
+        let dir = temp_dir("corrupt");
+        let lists = dir.join("lists.json");
+        fs::write(&lists, r#"{"a": ["#).unwrap();
+        fs::write(backup_path(&lists, 1), "").unwrap();
+        fs::write(backup_path(&lists, 2), r#"{"b": []}"#).unwrap();
+        fs::write(backup_path(&lists, 3), r#"{"c": []}"#).unwrap();
+        let info = open(&lists);
+        assert_eq!(info.item_list_names(), ["b"]);
+        assert_eq!(info.restored_from, Some(backup_path(&lists, 2)));
+        assert_eq!(open(&backup_path(&lists, 3)).restored_from, None);
+    }
+
+    #[test]
+    fn a_corrupt_file_does_not_replace_a_backup() {
+        let dir = temp_dir("keep");
+        let lists = dir.join("lists.json");
+        fs::write(&lists, "corrupt").unwrap();
+        fs::write(backup_path(&lists, 1), r#"{"a": []}"#).unwrap();
+        let mut info = open(&lists);
+        info.create_list("b".into());
+        info.save(None).unwrap();
+        assert_eq!(names(&lists), ["a", "b"]);
+        assert_eq!(names(&backup_path(&lists, 1)), ["a"]);
+        assert!(!backup_path(&lists, 2).exists());
+    }
+
+    #[test]
+    fn saved_lists_read_back_the_same() {
+        let dir = temp_dir("save");
+        let lists = dir.join("lists.json");
+        let old = r#"{"Plain": [{"title": "Water the plants", "description": "twice"}]}"#;
+        fs::write(&lists, old).unwrap();
+        let mut info = open(&lists);
+        let mut full = Items::default();
+        full.set_sort(SortMode::Due);
+        full.add(item("Pay the rent", None));
+        full.archive.push(item("Book a dentist", None));
+        full.trash.push(item("Call the bank", None));
+        info.insert("Full".into(), full);
+        info.save_search("Smart".into(), "tag:home".into());
+        info.save(None).unwrap();
+        let read = open(&lists);
+        assert_eq!(read.searches, info.searches);
+        assert_eq!(read.item_list_names(), ["Full", "Plain"]);
+        for (name, list) in &info.lists {
+            let other = &read.lists[name];
+            assert_eq!(other.items.to_vec(), list.items.to_vec());
+            assert_eq!(other.archive, list.archive);
+            assert_eq!(other.trash, list.trash);
+            assert_eq!(other.sort, list.sort);
+        }
+        assert_eq!(&*read.lists["Plain"][0].description, "twice");
+    }
+
+    #[test]
+    fn writing_back_edits_archives_and_deletes() {
+        let edited = tagged("Paint the door", None);
+        let archived = tagged("Fix the sink", None);
+        let deleted = tagged("Clean the attic", None);
+        let subtask = item("Sort the boxes", Some(&deleted));
+        let untouched = item("Write the report", None);
+        let mut info = with_list(
+            "Home",
+            vec![
+                edited.clone(),
+                archived.clone(),
+                deleted.clone(),
+                subtask,
+                untouched,
+            ],
+        );
+        let (mut smart, sources) = info.smart_list("tag:home").unwrap();
+        assert_eq!(sources.len(), 3);
+        let x = smart.position(edited.id).unwrap();
+        smart.modify(x, |x| x.title = "Paint the front door".into());
+        let x = smart.position(archived.id).unwrap();
+        let x = smart.remove(x);
+        smart.archive.push(x);
+        let x = smart.position(deleted.id).unwrap();
+        smart.delete(x);
+        info.write_back(&smart, &sources);
+        let home = &info.lists["Home"];
+        assert_eq!(
+            titles(home.items.iter()),
+            ["Paint the front door", "Write the report"]
+        );
+        assert_eq!(titles(&home.archive), ["Fix the sink"]);
+        // The subtask wasn't in the smart list, but it follows its parent
+        assert_eq!(titles(&home.trash), ["Sort the boxes", "Clean the attic"]);
+    }
+
+    #[test]
+    fn writing_back_moves_items_with_their_subtasks() {
+        let parent = tagged("Plan the party", None);
+        let subtask = item("Send the invitations", Some(&parent));
+        let mut info = with_list("Home", vec![parent.clone(), subtask.clone()]);
+        let (smart, mut sources) = info.smart_list("tag:home").unwrap();
+        sources.insert(parent.id, "Work".into());
+        info.write_back(&smart, &sources);
+        assert!(info.lists["Home"].is_empty());
+        let work = &info.lists["Work"];
+        assert_eq!(
+            titles(work.items.iter()),
+            ["Plan the party", "Send the invitations"]
+        );
+        let x = work.position(subtask.id).unwrap();
+        assert_eq!(work[x].parent, Some(parent.id));
+    }
+
+    #[test]
+    fn writing_back_an_undone_addition_removes_it() {
+        let mut info = with_list("Home", vec![tagged("Mow the lawn", None)]);
+        let (before, mut sources) = info.smart_list("tag:home").unwrap();
+        let mut smart = before.clone();
+        let added = tagged("Rake the leaves", None);
+        sources.insert(added.id, "Home".into());
+        smart.add(added);
+        // Saving writes the addition to its list
+        info.write_back(&smart, &sources);
+        assert_eq!(info.lists["Home"].amount(), 2);
+        // Undoing it brings back the smart list from before, the sources stay
+        info.write_back(&before, &sources);
+        let home = &info.lists["Home"];
+        assert_eq!(titles(home.items.iter()), ["Mow the lawn"]);
+        assert!(home.trash.is_empty());
     }
 }
@@ -0,0 +1,15 @@
+//! Fixtures shared by the unit tests
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+
+/// A Sunday morning
+pub fn now() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2026, 10, 18)
+        .unwrap()
+        .and_hms_opt(9, 0, 0)
+        .unwrap()
+}
+
+/// [`now`] in the local timezone
+pub fn local_now() -> DateTime<Local> {
+    now().and_local_timezone(Local).unwrap()
+}
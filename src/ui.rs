@@ -1,6 +1,8 @@
 //! This module is responsible for handling all ui operations
 //! It uses an [`App`] instance for this
 
+use std::collections::HashMap;
+
 use ratatui::{
     layout::{Constraint, Direction, Layout},
     style::{Color, Style},
@@ -9,13 +11,20 @@ use ratatui::{
     Frame,
 };
 
+use chrono::{DateTime, Local, NaiveDateTime};
 use ratatui::{
     prelude::*,
     widgets::{Clear, ListState, Wrap},
 };
 
 use crate::{
-    app::{App, CurrentEdit, CurrentSelection, ScreenLayout, State, Substate, SubstateMode},
+    app::{
+        App, CurrentEdit, CurrentSelection, ListChoiceState, ScreenLayout, State, Substate,
+        SubstateMode,
+    },
+    filter::Filter,
+    fuzzy_match, help,
+    parse::todo::{self, format_tags, Field, Id, Lookup, Priority},
     popup::Popup,
     query,
 };
@@ -30,11 +39,19 @@ pub fn ui(frame: &mut Frame, app: &App) {
                 .direction(Direction::Vertical)
                 .constraints([
                     Constraint::Min(1),
+                    Constraint::Length(u16::from(state.status.is_some())),
                     Constraint::Length(u16::from(state.substate.is_some())),
                 ])
                 .split(frame.size());
+            if let Some(status) = &state.status {
+                frame.render_widget(
+                    Text::raw(status.as_str()).style(Style::default().fg(Color::Yellow)),
+                    chunks[1],
+                );
+            }
             if let Some(substate) = &state.substate {
-                substate.render(substate.in_state, frame, chunks[1]);
+                let error = state.filter().and_then(Result::err);
+                substate.render(substate.in_state, error, frame, chunks[2]);
             }
             match state.current_selection {
                 ref a @ (CurrentSelection::Menu | CurrentSelection::Description) => {
@@ -43,112 +60,463 @@ pub fn ui(frame: &mut Frame, app: &App) {
                         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
                         .split(chunks[0]);
 
-                    draw_selection(frame, chunks[0], state, a);
-                    draw_info(frame, chunks[1], state, a);
+                    let items = state.lookup(&app.static_information);
+                    draw_selection(frame, chunks[0], state, &items, a);
+                    draw_info(frame, chunks[1], state, &items, a);
                     // Used to draw on top of the menu
                 }
             }
             if let Some(popup) = &state.popup {
                 match popup {
-                    Popup::Edit {
-                        ref title,
-                        ref description,
-                        ref editing,
-                        ..
-                    } => {
-                        render_title_desc(title, description, editing, frame);
+                    Popup::Edit { .. } => render_edit(popup, frame),
+                    Popup::Help(selected) => render_help(frame, app, state, *selected),
+                    Popup::ConfirmQuit => render_quit_confirm(frame),
+                    Popup::Archive(selected) => render_bin(
+                        frame,
+                        "Archive",
+                        "r: restore, q: close",
+                        &state.current_data.archive,
+                        *selected,
+                    ),
+                    Popup::Trash(selected) => render_bin(
+                        frame,
+                        "Trash",
+                        "r: restore, d: purge, q: close",
+                        &state.current_data.trash,
+                        *selected,
+                    ),
+                    Popup::ConfirmDelete => render_delete_confirm(frame, state),
+                    Popup::History(scroll) => render_history(frame, state, *scroll),
+                    Popup::PickList { selected, copy } => {
+                        render_pick_list(frame, app, state, *selected, *copy);
                     }
-                    Popup::Help(selected) => {
-                        let area = centered_rect(60, 60, frame.size());
-                        frame.render_widget(Clear, area);
-                        let (substate_control, opts) = {
-                            if let Some(Substate {
-                                in_state,
-                                substate_mode,
-                            }) = &state.substate
-                            {
-                                (
-                                    *in_state,
-                                    match substate_mode {
-                                        SubstateMode::Filter(x) => query(
-                                            app.static_information.help.items.to_vec(),
-                                            x.as_str(),
-                                        ),
-                                    },
-                                )
-                            } else {
-                                (
-                                    false,
-                                    app.static_information
-                                        .help
-                                        .items
-                                        .iter()
-                                        .cloned()
-                                        .enumerate()
-                                        .collect(),
-                                )
-                            }
-                        };
-                        // HACK: This only renders the cursor at the top while searching The cursor
-                        // automatically jumps back to its previous position afterward
-                        let selected = if substate_control { 0 } else { *selected };
-                        let chunks = Layout::default()
-                            .direction(Direction::Horizontal)
-                            .constraints(Constraint::from_percentages([30, 70]))
-                            .split(area);
-                        let mut state =
-                            ListState::with_selected(ListState::default(), Some(selected));
-                        let description = &opts.get(selected);
-                        let text = List::new(opts.iter().map(|x| x.1 .0 .0.to_string()))
-                            .block(Block::default().title("Help").borders(Borders::ALL))
-                            .scroll_padding(3)
-                            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
-                        frame.render_stateful_widget(text, chunks[0], &mut state);
-                        let description = Paragraph::new(Text::raw(
-                            description.map_or(String::new(), |description| {
-                                description.1 .0 .1.to_string()
-                            }),
-                        ))
-                        .block(Block::default().title("Desc").borders(Borders::ALL));
-                        frame.render_widget(description, chunks[1]);
+                    Popup::ListName { name, action } => {
+                        render_line_input(action.title(), name, frame);
                     }
+                    // Lists are only managed from the list picker
+                    Popup::ConfirmDeleteList(_) => (),
                 }
             }
         }
-        ScreenLayout::ListChoice => {
-            // TODO: Make it do something
+        ScreenLayout::ListChoice(ref state) => draw_list_choice(frame, app, state),
+    }
+}
+
+/// Draws the picker that shows every list
+fn draw_list_choice(frame: &mut Frame, app: &App, state: &ListChoiceState) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(u16::from(state.status.is_some())),
+        ])
+        .split(frame.size());
+    if let Some(status) = &state.status {
+        frame.render_widget(
+            Text::raw(status.as_str()).style(Style::default().fg(Color::Yellow)),
+            chunks[1],
+        );
+    }
+
+    let info = &app.static_information;
+    let names = info.list_names().into_iter().map(|x| {
+        info.searches.get(x).map_or_else(
+            || Line::raw(format!("{x} ({})", info.lists[x].amount())),
+            |search| {
+                Line::from(vec![
+                    Span::raw(x.as_str()),
+                    Span::styled(format!(" /{search}"), Color::Blue),
+                ])
+            },
+        )
+    });
+    let mut list_state = ListState::with_selected(ListState::default(), state.selected);
+    let list = List::new(names)
+        .block(
+            Block::bordered()
+                .title("Lists")
+                .title_bottom(
+                    "Enter: open, A: all lists, a: new, r: rename, c: copy, d: delete, q: quit",
+                )
+                .style(Color::Green),
+        )
+        .scroll_padding(3)
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, centered_rect(60, 80, chunks[0]), &mut list_state);
+
+    match state.popup {
+        Some(Popup::ConfirmQuit) => render_quit_confirm(frame),
+        Some(Popup::ListName {
+            ref name,
+            ref action,
+        }) => render_line_input(action.title(), name, frame),
+        Some(Popup::ConfirmDeleteList(ref name))
+            if app.static_information.searches.contains_key(name) =>
+        {
+            render_confirm(
+                "Delete search",
+                &format!("Delete the search {name}? Its items stay where they are\n\n(y) delete\n(n) cancel"),
+                frame,
+            );
         }
+        Some(Popup::ConfirmDeleteList(ref name)) => render_confirm(
+            "Delete list",
+            &format!("Delete {name} and all of its items?\n\n(y) delete\n(n) cancel"),
+            frame,
+        ),
+        _ => (),
     }
 }
 
-/// draws the associated inforation with the current item
-fn draw_info(frame: &mut Frame, chunk: Rect, state: &State, selection: &CurrentSelection) {
-    let info = Paragraph::new(Text::raw(state.selected.map_or_else(String::new, |x| {
-        state.current_data[x].description.to_string()
-    })))
-    .block(
-        Block::bordered().style(if matches!(selection, CurrentSelection::Description) {
-            Color::Green
+/// Draws the help popup, filtered by the substate if there is one
+fn render_help(frame: &mut Frame, app: &App, state: &State, selected: usize) {
+    let area = centered_rect(60, 60, frame.size());
+    frame.render_widget(Clear, area);
+    let mut search = "";
+    let (substate_control, opts) = {
+        if let Some(Substate {
+            in_state,
+            substate_mode,
+        }) = &state.substate
+        {
+            (
+                *in_state,
+                match substate_mode {
+                    SubstateMode::Filter(x) => {
+                        search = x;
+                        query(app.static_information.help.items.to_vec(), x.as_str())
+                    }
+                },
+            )
         } else {
-            Color::White
-        }),
+            (
+                false,
+                app.static_information
+                    .help
+                    .items
+                    .iter()
+                    .cloned()
+                    .enumerate()
+                    .collect(),
+            )
+        }
+    };
+    // HACK: This only renders the cursor at the top while searching The cursor
+    // automatically jumps back to its previous position afterward
+    let selected = if substate_control { 0 } else { selected };
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(Constraint::from_percentages([30, 70]))
+        .split(area);
+    let mut state = ListState::with_selected(ListState::default(), Some(selected));
+    // The search looks at the key and its description separated by a space
+    let positions = |help::Item((key, description)): &help::Item| {
+        let positions = fuzzy_match(&format!("{key} {description}"), search)
+            .map(|x| x.positions)
+            .unwrap_or_default();
+        let split = key.chars().count() + 1;
+        let (key_positions, description_positions) =
+            positions.into_iter().partition::<Vec<_>, _>(|&x| x < split);
+        let description_positions = description_positions
+            .into_iter()
+            .map(|x| x - split)
+            .collect::<Vec<_>>();
+        (key_positions, description_positions)
+    };
+    let description = &opts.get(selected);
+    let text = List::new(
+        opts.iter()
+            .map(|x| highlight(&x.1 .0 .0, &positions(&x.1).0, Style::default())),
     )
-    .wrap(Wrap { trim: false })
-    .scroll((
-        state.selected.map_or(0, |x| {
-            state.current_data[x]
-                .description_scroll
-                .try_into()
-                .expect("Corgats! You wasted time")
-        }),
-        0,
-    ));
+    .block(Block::default().title("Help").borders(Borders::ALL))
+    .scroll_padding(3)
+    .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(text, chunks[0], &mut state);
+    let description = Paragraph::new(description.map_or_else(Line::default, |description| {
+        highlight(
+            &description.1 .0 .1,
+            &positions(&description.1).1,
+            Style::default(),
+        )
+    }))
+    .block(Block::default().title("Desc").borders(Borders::ALL));
+    frame.render_widget(description, chunks[1]);
+}
+
+/// The positions in `field` of the item where the words searched for by
+/// `filter` are found best
+fn matched_positions(item: &todo::Item, filter: Option<&Filter>, field: Field) -> Vec<usize> {
+    let mut positions = filter
+        .map(Filter::words)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|x| item.best_match(x))
+        .filter(|(x, _)| *x == field)
+        .flat_map(|(_, x)| x.positions)
+        .collect::<Vec<_>>();
+    positions.sort_unstable();
+    positions.dedup();
+    positions
+}
+
+/// `text` with the chars at `positions` highlighted, see [`crate::Match`].
+/// The rest of the text is drawn with `style`
+fn highlight<'a>(text: &str, positions: &[usize], style: Style) -> Line<'a> {
+    let mut line = Line::default();
+    let mut run = String::new();
+    let mut matched = false;
+    for (i, c) in text.chars().enumerate() {
+        if positions.contains(&i) != matched {
+            push_run(&mut line, std::mem::take(&mut run), matched, style);
+            matched = !matched;
+        }
+        run.push(c);
+    }
+    push_run(&mut line, run, matched, style);
+    line
+}
+
+/// Like [`highlight`], but for text that may span multiple lines
+fn highlight_lines<'a>(text: &str, positions: &[usize]) -> Vec<Line<'a>> {
+    let mut start = 0;
+    text.split('\n')
+        .map(|line| {
+            let length = line.chars().count();
+            let positions = positions
+                .iter()
+                .filter(|&&x| x >= start && x < start + length)
+                .map(|x| x - start)
+                .collect::<Vec<_>>();
+            start += length + 1;
+            highlight(line, &positions, Style::default())
+        })
+        .collect()
+}
+
+fn push_run(line: &mut Line, run: String, matched: bool, style: Style) {
+    if run.is_empty() {
+        return;
+    }
+    line.push_span(if matched {
+        Span::styled(
+            run,
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::styled(run, style)
+    });
+}
+
+/// Asks whether the selected item should really be deleted
+fn render_delete_confirm(frame: &mut Frame, state: &State) {
+    let Some(selected) = state.selected_item() else {
+        return;
+    };
+    let title = &state.current_data[selected].title;
+    let subtasks = state.current_data.descendants(selected).len();
+    let text = match subtasks {
+        0 => format!("Delete \"{title}\"?"),
+        1 => format!("Delete \"{title}\" and its subtask?"),
+        x => format!("Delete \"{title}\" and its {x} subtasks?"),
+    };
+    render_confirm(
+        "Delete item",
+        &format!("{text}\n\n(y) delete\n(n) cancel"),
+        frame,
+    );
+}
+
+/// Draws the archive or the trash of the current list
+fn render_bin(frame: &mut Frame, title: &str, hints: &str, items: &[todo::Item], selected: usize) {
+    let area = centered_rect(60, 60, frame.size());
+    frame.render_widget(Clear, area);
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(Constraint::from_percentages([40, 60]))
+        .split(area);
+    let mut list_state = ListState::with_selected(ListState::default(), Some(selected));
+    let list = List::new(items.iter().map(|x| x.title.to_string()))
+        .block(
+            Block::default()
+                .title(title)
+                .title_bottom(hints)
+                .borders(Borders::ALL),
+        )
+        .scroll_padding(3)
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, chunks[0], &mut list_state);
+    let description = Paragraph::new(Text::raw(
+        items
+            .get(selected)
+            .map_or_else(String::new, |x| x.description.to_string()),
+    ))
+    .block(Block::default().title("Desc").borders(Borders::ALL))
+    .wrap(Wrap { trim: false });
+    frame.render_widget(description, chunks[1]);
+}
+
+/// Draws the lists the selected item can be moved or copied to
+fn render_pick_list(frame: &mut Frame, app: &App, state: &State, selected: usize, copy: bool) {
+    let area = centered_rect(40, 50, frame.size());
+    frame.render_widget(Clear, area);
+    let current = state.selected.map(|x| state.list_of(x));
+    let names = app
+        .static_information
+        .item_list_names()
+        .into_iter()
+        .map(|x| {
+            if Some(x.as_str()) == current {
+                format!("{x} (current)")
+            } else {
+                x.clone()
+            }
+        });
+    let mut list_state = ListState::with_selected(ListState::default(), Some(selected));
+    let list = List::new(names)
+        .block(
+            Block::bordered()
+                .title(if copy { "Copy to" } else { "Move to" })
+                .title_bottom("Enter: pick, q: cancel"),
+        )
+        .scroll_padding(3)
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+/// Draws the history of the selected item
+fn render_history(frame: &mut Frame, state: &State, scroll: usize) {
+    let Some(selected) = state.selected_item() else {
+        return;
+    };
+    let item = &state.current_data[selected];
+    let area = centered_rect(70, 60, frame.size());
+    frame.render_widget(Clear, area);
+    let format = |x: Option<DateTime<Local>>| {
+        x.map_or_else(
+            || "-".to_string(),
+            |x| x.format("%Y-%m-%d %H:%M").to_string(),
+        )
+    };
+    let mut lines = vec![Line::raw(format!(
+        "Created {}, modified {}",
+        format(item.created),
+        format(item.modified)
+    ))];
+    lines.extend(item.history.iter().map(|change| {
+        Line::from(vec![
+            Span::styled(
+                format!("{} ", change.at.format("%Y-%m-%d %H:%M")),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(
+                format!("{}: ", change.field),
+                Style::default().fg(Color::Cyan),
+            ),
+            Span::raw(format!("{:?} -> {:?}", change.old, change.new)),
+        ])
+    }));
+    let history = Paragraph::new(lines)
+        .block(
+            Block::bordered()
+                .title(format!("History of {}", item.title))
+                .title_bottom("q: close"),
+        )
+        .wrap(Wrap { trim: false })
+        .scroll((scroll.try_into().unwrap_or(u16::MAX), 0));
+    frame.render_widget(history, area);
+}
+
+/// draws the associated inforation with the current item
+fn draw_info(
+    frame: &mut Frame,
+    chunk: Rect,
+    state: &State,
+    items: &Lookup,
+    selection: &CurrentSelection,
+) {
+    let selected = state.selected_item();
+    let mut text = Text::default();
+    if let Some(item) = selected.map(|x| &state.current_data[x]) {
+        let describe = |(list, item): (&str, &todo::Item)| {
+            let style = if item.is_done() {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+            Line::styled(format!("  {} ({list})", item.title), style)
+        };
+        let blockers = item
+            .blocked_by
+            .iter()
+            .filter_map(|x| items.get(x).copied())
+            .map(describe)
+            .collect::<Vec<_>>();
+        if !blockers.is_empty() {
+            text.push_line(Line::styled("Blocked by:", Color::Red));
+            text.extend(blockers);
+        }
+        let dependents = item.dependents(items);
+        if !dependents.is_empty() {
+            text.push_line(Line::styled("Blocks:", Color::Yellow));
+            text.extend(dependents.into_iter().map(describe));
+        }
+        if !text.lines.is_empty() {
+            text.push_line("");
+        }
+        let filter = state.filter().and_then(Result::ok);
+        let positions = matched_positions(item, filter.as_ref(), Field::Description);
+        text.extend(highlight_lines(&item.description, &positions));
+    }
+    let info = Paragraph::new(text)
+        .block(
+            Block::bordered()
+                .title(selected.map_or_else(String::new, |x| {
+                    let item = &state.current_data[x];
+                    let mut dates = Vec::new();
+                    if let Some(completed) = item.completed {
+                        dates.push(format!("Done {}", completed.format("%Y-%m-%d %H:%M")));
+                    }
+                    if let Some(due) = item.due {
+                        dates.push(format!("Due {due}"));
+                    }
+                    if let Some(scheduled) = item.scheduled {
+                        dates.push(format!("Scheduled {scheduled}"));
+                    }
+                    if let Some(repeat) = &item.repeat {
+                        dates.push(format!("Repeats {repeat}"));
+                    }
+                    dates.join(" | ")
+                }))
+                .style(if matches!(selection, CurrentSelection::Description) {
+                    Color::Green
+                } else {
+                    Color::White
+                }),
+        )
+        .wrap(Wrap { trim: false })
+        .scroll((
+            selected.map_or(0, |x| {
+                state.current_data[x]
+                    .description_scroll
+                    .try_into()
+                    .expect("Corgats! You wasted time")
+            }),
+            0,
+        ));
 
     frame.render_widget(info, chunk);
 }
 
 /// Draws all things that are interactable
-fn draw_selection(frame: &mut Frame, chunk: Rect, state: &State, selection: &CurrentSelection) {
+fn draw_selection(
+    frame: &mut Frame,
+    chunk: Rect,
+    state: &State,
+    items: &Lookup,
+    selection: &CurrentSelection,
+) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Length(3), Constraint::Min(1)])
@@ -165,9 +533,27 @@ fn draw_selection(frame: &mut Frame, chunk: Rect, state: &State, selection: &Cur
 
     frame.render_widget(title, chunks[0]);
 
-    let mut list_state = ListState::with_selected(ListState::default(), state.selected);
-    let list = List::new(state.current_data.titles())
-        .block(Block::bordered().title("List").style(
+    let now = Local::now().naive_local();
+    let filter = state.filter().and_then(Result::ok);
+    let tree = state.current_data.tree();
+    let rows = state
+        .visible_rows()
+        .into_iter()
+        .map(|(x, depth)| item_line(state, items, &tree, filter.as_ref(), (x, depth), now));
+    let mut list_title = format!("List [{}]", state.current_data.sort);
+    if let Some(tag) = &state.tag_filter {
+        list_title = format!("{list_title} #{tag}");
+    }
+    if state.group_by_tag {
+        list_title.push_str(" (by tag)");
+    }
+    if let Some(search) = state.search() {
+        list_title = format!("{list_title} /{search}");
+    }
+
+    let mut list_state = ListState::with_selected(ListState::default(), state.selected_position());
+    let list = List::new(rows)
+        .block(Block::bordered().title(list_title).style(
             if matches!(selection, CurrentSelection::Menu) {
                 Color::Green
             } else {
@@ -180,6 +566,97 @@ fn draw_selection(frame: &mut Frame, chunk: Rect, state: &State, selection: &Cur
     frame.render_stateful_widget(list, chunks[1], &mut list_state);
 }
 
+/// A single row of the list
+fn item_line<'a>(
+    state: &State,
+    items: &Lookup,
+    tree: &HashMap<Option<Id>, Vec<usize>>,
+    filter: Option<&Filter>,
+    (x, depth): (usize, usize),
+    now: NaiveDateTime,
+) -> Line<'a> {
+    let item = &state.current_data[x];
+    let mut line = Line::raw("  ".repeat(depth));
+    let (done, all) = state.current_data.progress(tree, x);
+    if all != 0 {
+        line.push_span(if item.collapsed { "▸ " } else { "▾ " });
+    }
+    if let Some(priority) = item.priority {
+        line.push_span(Span::styled(
+            format!("({priority}) "),
+            Style::default().fg(priority_color(priority)),
+        ));
+    }
+    let positions = |field| matched_positions(item, filter, field);
+    line.spans
+        .extend(highlight(&item.title, &positions(Field::Title), Style::default()).spans);
+    if all != 0 {
+        line.push_span(Span::styled(
+            format!(" {done}/{all}"),
+            Style::default().fg(if done == all {
+                Color::Green
+            } else {
+                Color::DarkGray
+            }),
+        ));
+    }
+    if item.is_done() {
+        line = line.style(
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::CROSSED_OUT),
+        );
+    } else if let Some(due) = item.due {
+        let color = if due.is_overdue(now) {
+            Color::Red
+        } else if due.is_today(now) {
+            Color::Yellow
+        } else {
+            Color::DarkGray
+        };
+        line.push_span(Span::styled(
+            format!(" due {due}"),
+            Style::default().fg(color),
+        ));
+    }
+    if item.repeat.is_some() {
+        line.push_span(Span::styled(" ↻", Style::default().fg(Color::DarkGray)));
+    }
+    // Smart lists mix the items of several lists
+    if state.sources.is_some() {
+        line.push_span(Span::styled(
+            format!(" [{}]", state.list_of(item.id)),
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+    // Items that can't be worked on yet fade into the background
+    if item.is_blocked(items) {
+        line = line.style(Style::default().add_modifier(Modifier::DIM));
+    }
+    if !item.tags.is_empty() {
+        line.push_span(" ");
+        line.spans.extend(
+            highlight(
+                &format_tags(&item.tags),
+                &positions(Field::Tags),
+                Style::default().fg(Color::Cyan),
+            )
+            .spans,
+        );
+    }
+    line
+}
+
+/// The colour of the marker in front of an item with this priority
+const fn priority_color(priority: Priority) -> Color {
+    match priority {
+        Priority::A => Color::Red,
+        Priority::B => Color::Yellow,
+        Priority::C => Color::Blue,
+        Priority::D => Color::Gray,
+    }
+}
+
 /// This code is absolutely stolen from the ratatui json example
 /// Draws a centered rectangle
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -204,65 +681,144 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1] // Return the middle chunk
 }
 
-fn render_title_desc(title: &str, description: &str, editing: &CurrentEdit, frame: &mut Frame) {
-    let area = centered_rect(50, 50, frame.size());
+/// Draws a small popup asking the user to confirm something
+fn render_confirm(title: &str, text: &str, frame: &mut Frame) {
+    let area = centered_rect(40, 30, frame.size());
     frame.render_widget(Clear, area);
+    let confirm = Paragraph::new(Text::raw(text))
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .wrap(Wrap { trim: false });
+    frame.render_widget(confirm, area);
+}
 
-    let popup_block = Block::default().title("Edit").borders(Borders::ALL);
+/// Draws a popup with a single line of text being typed in
+fn render_line_input(title: &str, text: &str, frame: &mut Frame) {
+    let area = centered_rect(40, 20, frame.size());
+    let area = Rect {
+        height: area.height.min(3),
+        ..area
+    };
+    frame.render_widget(Clear, area);
+    let input = Paragraph::new(format!("{text}█")).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Green)),
+    );
+    frame.render_widget(input, area);
+}
+
+fn render_quit_confirm(frame: &mut Frame) {
+    render_confirm(
+        "Unsaved changes",
+        "There are unsaved changes.\n\n(s) save and quit\n(y) quit anyway\n(n) cancel",
+        frame,
+    );
+}
+
+fn render_edit(popup: &Popup, frame: &mut Frame) {
+    let Popup::Edit {
+        title,
+        description,
+        tags,
+        due,
+        scheduled,
+        repeat,
+        error,
+        editing,
+        ..
+    } = popup
+    else {
+        return;
+    };
+    let area = centered_rect(60, 70, frame.size());
+    frame.render_widget(Clear, area);
+
+    let popup_block = Block::default()
+        .title("Edit")
+        .title_bottom(error.as_ref().map_or_else(
+            || Line::from("Tab: next field, Enter: save, Esc: cancel"),
+            |e| Line::styled(e.as_str(), Style::default().fg(Color::Red)),
+        ))
+        .borders(Borders::ALL);
     frame.render_widget(popup_block, area);
 
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(3),
+            Constraint::Length(3),
+        ])
         .margin(1)
         .split(area);
+    let date_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(Constraint::from_percentages([34, 33, 33]))
+        .split(chunks[3]);
 
-    let title_block = Block::default()
-        .title("Title")
-        .borders(Borders::ALL)
-        .border_style(
-            Style::default().fg(if matches!(editing, CurrentEdit::Title) {
-                Color::Green
-            } else {
-                Color::White
-            }),
-        );
-    let title_text = Paragraph::new(
-        title
-            .chars()
-            .chain(if matches!(editing, CurrentEdit::Title) {
-                Some('█')
-            } else {
-                None
-            })
-            .collect::<String>(),
-    )
-    .block(title_block);
-    frame.render_widget(title_text, chunks[0]);
+    frame.render_widget(
+        edit_field("Title", title, matches!(editing, CurrentEdit::Title)),
+        chunks[0],
+    );
+    frame.render_widget(
+        edit_field(
+            "Description",
+            description,
+            matches!(editing, CurrentEdit::Body),
+        )
+        .wrap(Wrap { trim: false }),
+        chunks[1],
+    );
+    frame.render_widget(
+        edit_field("Tags", tags, matches!(editing, CurrentEdit::Tags)),
+        chunks[2],
+    );
+    frame.render_widget(
+        edit_field(
+            "Due (e.g. fri 17:00)",
+            due,
+            matches!(editing, CurrentEdit::Due),
+        ),
+        date_chunks[0],
+    );
+    frame.render_widget(
+        edit_field(
+            "Scheduled (e.g. +3d)",
+            scheduled,
+            matches!(editing, CurrentEdit::Scheduled),
+        ),
+        date_chunks[1],
+    );
+    frame.render_widget(
+        edit_field(
+            "Repeat (e.g. weekly mon)",
+            repeat,
+            matches!(editing, CurrentEdit::Repeat),
+        ),
+        date_chunks[2],
+    );
+}
 
-    let description_block = Block::default()
-        .title("Description")
+/// A single bordered text field of the edit popup.
+/// The field that is being edited is highlighted and has a cursor
+fn edit_field<'a>(name: &'a str, text: &str, active: bool) -> Paragraph<'a> {
+    let block = Block::default()
+        .title(name)
         .borders(Borders::ALL)
-        .border_style(
-            Style::default().fg(if matches!(editing, CurrentEdit::Body) {
-                Color::Green
-            } else {
-                Color::White
-            }),
-        );
-    let description_text = Paragraph::new(
-        description
-            .chars()
-            .chain(if matches!(editing, CurrentEdit::Body) {
-                Some('█')
-            } else {
-                None
-            })
+        .border_style(Style::default().fg(if active { Color::Green } else { Color::White }));
+    Paragraph::new(
+        text.chars()
+            .chain(if active { Some('█') } else { None })
             .collect::<String>(),
     )
-    .block(description_block)
-    .wrap(Wrap { trim: false });
-    frame.render_widget(description_text, chunks[1]);
+    .block(block)
 }
 
 impl Substate {
@@ -271,21 +827,30 @@ impl Substate {
     ///
     /// # Parameters
     /// in_state are we currently in the substate?
+    /// error: Why the filter couldn't be read
     /// frame: The global frame to draw on
     /// chunk: The Rectangle which we are allowed to modify
-    pub fn render(&self, in_state: bool, frame: &mut Frame, chunk: Rect) {
+    pub fn render(&self, in_state: bool, error: Option<String>, frame: &mut Frame, chunk: Rect) {
         match &self.substate_mode {
-            SubstateMode::Filter(x) => frame.render_widget(
-                Text::raw({
-                    let mut ret = format!("/{x}");
-                    if in_state {
-                        ret.push('█');
-                    }
-                    ret
-                })
-                .style(Style::default().fg(Color::Blue)),
-                chunk,
-            ),
+            SubstateMode::Filter(x) => {
+                let mut line = Line::styled(
+                    {
+                        let mut ret = format!("/{x}");
+                        if in_state {
+                            ret.push('█');
+                        }
+                        ret
+                    },
+                    Style::default().fg(Color::Blue),
+                );
+                if let Some(error) = error {
+                    line.push_span(Span::styled(
+                        format!("  {error}"),
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+                frame.render_widget(line, chunk);
+            }
         }
     }
 }
@@ -0,0 +1,107 @@
+//! Keeps track of changes so that they can be undone and redone
+use std::collections::VecDeque;
+
+/// How many changes can be undone by default. Every change keeps a copy of
+/// the whole list, so this is kept small
+pub const DEFAULT_DEPTH: usize = 20;
+
+/// The changes that can be undone and redone. Each one is stored as a
+/// description of the change and `T`, how things were before (or after) it
+#[derive(Debug)]
+pub struct History<T> {
+    undo: VecDeque<(String, T)>,
+    redo: Vec<(String, T)>,
+    /// How many changes can be undone at most. The oldest ones are forgotten
+    pub depth: usize,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth: DEFAULT_DEPTH,
+        }
+    }
+}
+
+impl<T> History<T> {
+    /// Remembers how things were before a change. Changes that have been undone
+    /// can't be redone after this
+    pub fn record(&mut self, description: String, before: T) {
+        self.redo.clear();
+        self.undo.push_back((description, before));
+        self.trim();
+    }
+
+    /// Takes the latest change that can be undone together with how things were
+    /// before it. `now` turns that into how things are now so that the change
+    /// can be redone
+    pub fn undo(&mut self, now: impl FnOnce(&T) -> T) -> Option<(String, T)> {
+        let (description, before) = self.undo.pop_back()?;
+        self.redo.push((description.clone(), now(&before)));
+        Some((description, before))
+    }
+
+    /// The opposite of [`Self::undo`]
+    pub fn redo(&mut self, now: impl FnOnce(&T) -> T) -> Option<(String, T)> {
+        let (description, after) = self.redo.pop()?;
+        self.undo.push_back((description.clone(), now(&after)));
+        self.trim();
+        Some((description, after))
+    }
+
+    fn trim(&mut self) {
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A history of a number that was counted up from 0 to `to`
+    fn counted(to: usize) -> History<usize> {
+        let mut history = History::default();
+        for x in 0..to {
+            history.record(format!("adding {}", x + 1), x);
+        }
+        history
+    }
+
+    #[test]
+    fn undoing_and_redoing() {
+        let mut history = counted(3);
+        assert_eq!(history.undo(|_| 3), Some(("adding 3".into(), 2)));
+        assert_eq!(history.undo(|_| 2), Some(("adding 2".into(), 1)));
+        assert_eq!(history.redo(|_| 1), Some(("adding 2".into(), 2)));
+        assert_eq!(history.redo(|_| 2), Some(("adding 3".into(), 3)));
+        assert_eq!(history.redo(|_| 3), None);
+        assert_eq!(history.undo(|_| 3), Some(("adding 3".into(), 2)));
+    }
+
+    #[test]
+    fn new_changes_forget_what_was_undone() {
+        let mut history = counted(2);
+        history.undo(|_| 2);
+        history.record("adding 10".into(), 1);
+        assert_eq!(history.redo(|_| 11), None);
+        assert_eq!(history.undo(|_| 11), Some(("adding 10".into(), 1)));
+        assert_eq!(history.undo(|_| 1), Some(("adding 1".into(), 0)));
+    }
+
+    #[test]
+    fn only_the_latest_changes_are_kept() {
+        let mut history = counted(DEFAULT_DEPTH + 5);
+        let mut undone = 0;
+        while history.undo(|&x| x + 1).is_some() {
+            undone += 1;
+        }
+        assert_eq!(undone, DEFAULT_DEPTH);
+        // Redoing doesn't go over the depth either
+        while history.redo(|&x| x - 1).is_some() {}
+        assert_eq!(history.undo.len(), DEFAULT_DEPTH);
+    }
+}